@@ -4,6 +4,6 @@ mod common;
 
 fn main() -> anyhow::Result<()> {
     let mut device = common::initialize()?;
-    device.set_backlight(0, 0)?;
+    device.set_brightness(0)?;
     Ok(())
 }
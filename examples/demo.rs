@@ -2,9 +2,7 @@
 
 mod common;
 
-use cfa635::{Device, Key, Report};
-use std::thread;
-use std::time::Duration;
+use cfa635::{Device, Key, LedColor, Report};
 
 fn main() -> anyhow::Result<()> {
     let device = common::initialize()?;
@@ -32,7 +30,7 @@ impl Menu {
                 name: pad(b"Backlight".to_vec()),
                 value: 100,
                 max_value: 100,
-                setter: |dev, val| dev.set_backlight(val, val),
+                setter: |dev, val| dev.set_brightness(val),
             },
             MenuEntry {
                 name: pad(b"Contrast".to_vec()),
@@ -44,25 +42,61 @@ impl Menu {
                 name: pad(b"LED 0 (Green)".to_vec()),
                 value: 0,
                 max_value: 1,
-                setter: |dev, val| dev.set_led(0, 0, val * 100),
+                setter: |dev, val| {
+                    dev.set_led_color(
+                        0,
+                        if val == 1 {
+                            LedColor::GREEN
+                        } else {
+                            LedColor::OFF
+                        },
+                    )
+                },
             },
             MenuEntry {
                 name: pad(b"LED 1 (Red)".to_vec()),
                 value: 0,
                 max_value: 1,
-                setter: |dev, val| dev.set_led(1, val * 100, 0),
+                setter: |dev, val| {
+                    dev.set_led_color(
+                        1,
+                        if val == 1 {
+                            LedColor::RED
+                        } else {
+                            LedColor::OFF
+                        },
+                    )
+                },
             },
             MenuEntry {
                 name: pad(b"LED 2 (Yellow)".to_vec()),
                 value: 0,
                 max_value: 1,
-                setter: |dev, val| dev.set_led(2, val * 100, val * 100),
+                setter: |dev, val| {
+                    dev.set_led_color(
+                        2,
+                        if val == 1 {
+                            LedColor::YELLOW
+                        } else {
+                            LedColor::OFF
+                        },
+                    )
+                },
             },
             MenuEntry {
                 name: pad(b"LED 3 (Orange)".to_vec()),
                 value: 0,
                 max_value: 1,
-                setter: |dev, val| dev.set_led(3, val * 100, val * 50),
+                setter: |dev, val| {
+                    dev.set_led_color(
+                        3,
+                        if val == 1 {
+                            LedColor::ORANGE
+                        } else {
+                            LedColor::OFF
+                        },
+                    )
+                },
             },
         ];
 
@@ -82,10 +116,8 @@ impl Menu {
 
     fn run(&mut self) -> anyhow::Result<()> {
         loop {
-            if let Some(report) = self.device.poll_report()? {
+            if let Some(report) = self.device.wait_report(None)? {
                 self.handle(report)?;
-            } else {
-                thread::sleep(Duration::from_millis(10));
             }
         }
     }
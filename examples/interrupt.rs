@@ -0,0 +1,38 @@
+//! Prints key events from a worker thread until Enter is pressed on stdin,
+//! then interrupts the worker and shuts down cleanly.
+
+mod common;
+
+use cfa635::{Error, Key};
+use std::io::{stdin, BufRead};
+use std::thread;
+
+fn main() -> anyhow::Result<()> {
+    let mut device = common::initialize()?;
+    device.configure_key_reporting(&[Key::Up, Key::Down, Key::Left, Key::Right], &[])?;
+
+    // The handle can be used from any thread to interrupt blocking calls on
+    // the device, even after the device has been moved away.
+    let interrupt = device.interrupt_handle();
+
+    let worker = thread::spawn(move || -> Result<(), Error> {
+        loop {
+            match device.wait_report(None) {
+                Ok(Some(report)) => println!("{}", report),
+                // Only returned when waiting with a timeout.
+                Ok(None) => {}
+                Err(Error::Interrupted) => {
+                    println!("worker interrupted, shutting down");
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    });
+
+    println!("Press keys on the device; press Enter here to quit.");
+    stdin().lock().lines().next();
+    interrupt.interrupt();
+    worker.join().expect("worker panicked")?;
+    Ok(())
+}
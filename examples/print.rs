@@ -1,17 +1,21 @@
 //! Reads stdin line-by-line, and displays those lines on the screen.
 
 mod common;
+use cfa635::{Framebuffer, NUM_COLUMNS};
 use std::io::{stdin, BufRead};
 
 fn main() -> anyhow::Result<()> {
     let mut device = common::initialize()?;
+    let mut fb = Framebuffer::new();
 
     let stdin = stdin();
     let handle = stdin.lock();
     for result in handle.lines() {
         let line = result?;
-        device.clear_screen()?;
-        device.set_text(0, 15, line.as_bytes())?;
+        let text = &line.as_bytes()[..line.len().min(usize::from(NUM_COLUMNS - 15))];
+        fb.clear();
+        fb.write(0, 15, text)?;
+        device.flush_framebuffer(&fb)?;
     }
 
     Ok(())
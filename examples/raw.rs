@@ -0,0 +1,25 @@
+//! Sends the "Get Hardware & Firmware Version" command (0x01) as a raw
+//! command, and prints the parts of the version string.
+//!
+//! This does by hand what `Device::version` does, as a starting point for
+//! commands that this crate does not wrap.
+
+mod common;
+
+use anyhow::Context;
+use cfa635::Version;
+
+fn main() -> anyhow::Result<()> {
+    let mut device = common::initialize()?;
+
+    let response = device.raw_transact(0x01, &[])?;
+    println!("raw response: {:02x?}", response);
+
+    // The response is a string of the form `CFA635:hX.Y,fW.Z`.
+    let version = Version::from_data(&response).context("invalid version response")?;
+    println!("model:    {}", version.model);
+    println!("hardware: {}", version.hardware);
+    println!("firmware: {}", version.firmware);
+
+    Ok(())
+}
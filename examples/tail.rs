@@ -0,0 +1,17 @@
+//! Shows the most recent lines from stdin on the screen, like `tail -f`.
+//!
+//! For example: `journalctl -f | cargo run --example tail /dev/ttyACM0`
+
+mod common;
+use cfa635::{LineOverflow, NUM_ROWS};
+use std::io::stdin;
+
+fn main() -> anyhow::Result<()> {
+    let mut device = common::initialize()?;
+    device.clear_screen()?;
+
+    // Runs until stdin is closed.
+    device.tail(stdin().lock(), NUM_ROWS.into(), LineOverflow::Wrap)?;
+
+    Ok(())
+}
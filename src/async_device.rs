@@ -0,0 +1,312 @@
+//! An asynchronous interface to the display, for use with tokio.
+
+use crate::codec::{Packet, PacketCodec};
+use crate::{
+    check_len, check_range, expected_response_len, limits, CursorStyle, Error, IntoLed, Report,
+    DEFAULT_BAUD_RATE, NUM_COLUMNS, NUM_ROWS,
+};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::{timeout_at, Instant};
+use tokio_serial::SerialPortBuilderExt;
+
+/// How long to wait for the response to a command.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A byte stream that an [`AsyncDevice`] can communicate over.
+trait AsyncTransport: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T> AsyncTransport for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
+
+/// An asynchronous version of [`Device`](crate::Device), for applications
+/// built on tokio.
+///
+/// Requires the `tokio` feature. Each method mirrors the `Device` method of
+/// the same name; see there for details. Waiting for responses and reports
+/// does not block the runtime.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), cfa635::Error> {
+/// use cfa635::AsyncDevice;
+///
+/// let mut device = AsyncDevice::new("/dev/ttyACM0")?;
+/// device.clear_screen().await?;
+/// device.set_text(0, 0, b"Hello, world!").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncDevice {
+    codec: PacketCodec<Box<dyn AsyncTransport>>,
+    report_buffer: VecDeque<Report>,
+}
+
+impl AsyncDevice {
+    /// Connects to the device at the given serial port, at the default baud
+    /// rate ([`DEFAULT_BAUD_RATE`]).
+    ///
+    /// Must be called from within a tokio runtime, with its time driver
+    /// enabled (used for response timeouts).
+    pub fn new<P: AsRef<str>>(path: P) -> Result<Self, Error> {
+        Self::with_baud(path, DEFAULT_BAUD_RATE)
+    }
+
+    /// Like [`AsyncDevice::new`], but at the given baud rate.
+    pub fn with_baud<P: AsRef<str>>(path: P, baud_rate: u32) -> Result<Self, Error> {
+        let port = tokio_serial::new(path.as_ref(), baud_rate).open_native_async()?;
+        Ok(Self::with_transport(Box::new(port)))
+    }
+
+    fn with_transport(transport: Box<dyn AsyncTransport>) -> Self {
+        Self {
+            codec: PacketCodec::new(transport),
+            report_buffer: VecDeque::new(),
+        }
+    }
+
+    async fn send(&mut self, packet: &Packet) -> Result<(), Error> {
+        log::trace!("sending {:?}", packet);
+        self.codec.write_packet_async(packet).await?;
+        Ok(())
+    }
+
+    /// Reads the next valid packet, skipping bytes to resynchronize with the
+    /// device as [`Device`](crate::Device) does.
+    ///
+    /// Returns `None` if `deadline` passes first. Reading is cancel-safe, so
+    /// the bytes of a packet that arrived only in part are kept, and the next
+    /// call continues the same packet.
+    async fn recv(&mut self, deadline: Option<Instant>) -> Result<Option<Packet>, Error> {
+        let read = self.codec.read_packet_resync_async();
+        let (packet, skipped) = match deadline {
+            Some(deadline) => match timeout_at(deadline, read).await {
+                Ok(result) => result?,
+                Err(_) => return Ok(None),
+            },
+            None => read.await?,
+        };
+        if skipped > 0 {
+            log::warn!("skipped {} bytes to resynchronize with the device", skipped);
+        }
+        log::trace!("received {:?}", packet);
+        Ok(Some(packet))
+    }
+
+    async fn transact(&mut self, packet: &Packet) -> Result<Packet, Error> {
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        self.send(packet).await?;
+        loop {
+            let Some(response) = self.recv(Some(deadline)).await? else {
+                log::warn!("timed out waiting for a response to {:?}", packet);
+                return Err(Error::Timeout);
+            };
+            let resp_class = response.packet_type() >> 6;
+            let resp_code = response.packet_type() & limits::MAX_COMMAND;
+            if resp_class == 0b10 {
+                if let Some(report) = Report::from_raw(&response) {
+                    self.report_buffer.push_back(report);
+                }
+            } else if resp_class == 0b01 && resp_code == packet.packet_type() {
+                match expected_response_len(packet) {
+                    Some(len) if len != response.data().len() => {
+                        log::warn!("expected {} bytes in response, got {:?}", len, response);
+                        return Err(Error::InvalidRead);
+                    }
+                    _ => return Ok(response),
+                }
+            } else if resp_class == 0b11 && resp_code == packet.packet_type() {
+                return Err(Error::ReturnedError {
+                    command: packet.packet_type(),
+                    data: response.data().to_vec(),
+                });
+            } else {
+                log::warn!("unexpected packet received: {:?}", response);
+            }
+        }
+    }
+
+    /// See [`Device::ping`](crate::Device::ping).
+    pub async fn ping(&mut self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let data = &data[..data.len().min(limits::MAX_PING_LEN)];
+        let response = self.transact(&Packet::new(0x00, data)).await?;
+        Ok(response.data().to_vec())
+    }
+
+    /// See [`Device::clear_screen`](crate::Device::clear_screen).
+    pub async fn clear_screen(&mut self) -> Result<(), Error> {
+        self.transact(&Packet::new(0x06, &[])).await?;
+        Ok(())
+    }
+
+    /// See [`Device::set_text`](crate::Device::set_text).
+    pub async fn set_text(&mut self, row: u8, col: u8, text: &[u8]) -> Result<(), Error> {
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        check_len("text", text, usize::from(NUM_COLUMNS - col))?;
+        let mut data = vec![col, row];
+        data.extend_from_slice(text);
+        self.transact(&Packet::new(0x1f, &data)).await?;
+        Ok(())
+    }
+
+    /// See [`Device::set_cursor_position`](crate::Device::set_cursor_position).
+    pub async fn set_cursor_position(&mut self, row: u8, col: u8) -> Result<(), Error> {
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        self.transact(&Packet::new(0x0b, &[col, row])).await?;
+        Ok(())
+    }
+
+    /// See [`Device::set_cursor_style`](crate::Device::set_cursor_style).
+    pub async fn set_cursor_style(&mut self, style: CursorStyle) -> Result<(), Error> {
+        self.transact(&Packet::new(0x0c, &[style as u8])).await?;
+        Ok(())
+    }
+
+    /// Sets the contrast of the screen; see
+    /// [`Device::set_contrast`](crate::Device::set_contrast).
+    pub async fn set_contrast(&mut self, contrast: u8) -> Result<(), Error> {
+        self.transact(&Packet::new(0x0d, &[contrast.min(limits::MAX_CONTRAST)]))
+            .await?;
+        Ok(())
+    }
+
+    /// Sets the backlight brightness of the screen and keypad; see
+    /// [`Device::set_backlight`](crate::Device::set_backlight).
+    pub async fn set_backlight(&mut self, screen: u8, keypad: u8) -> Result<(), Error> {
+        self.transact(&Packet::new(0x0e, &[screen.min(100), keypad.min(100)]))
+            .await?;
+        Ok(())
+    }
+
+    /// See [`Device::set_led`](crate::Device::set_led).
+    pub async fn set_led(&mut self, index: impl IntoLed, red: u8, green: u8) -> Result<(), Error> {
+        let index = index.into_led()?.index();
+        let (red_gpio, green_gpio) = crate::Device::led_gpio_pins(index).unwrap();
+        self.transact(&Packet::new(0x22, &[red_gpio, red])).await?;
+        self.transact(&Packet::new(0x22, &[green_gpio, green]))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the next report, or `None` if there are none available right
+    /// now; see [`Device::poll_report`](crate::Device::poll_report).
+    pub async fn poll_report(&mut self) -> Result<Option<Report>, Error> {
+        self.wait_report(Some(Duration::ZERO)).await
+    }
+
+    /// Waits for the next report, returning `None` if none arrives within
+    /// `timeout`; see [`Device::wait_report`](crate::Device::wait_report).
+    pub async fn wait_report(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Report>, Error> {
+        if let Some(report) = self.report_buffer.pop_front() {
+            return Ok(Some(report));
+        }
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let Some(packet) = self.recv(deadline).await? else {
+                return Ok(None);
+            };
+            if let Some(report) = Report::from_raw(&packet) {
+                return Ok(Some(report));
+            }
+            log::debug!("discarding non-report packet {:?}", packet);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Key;
+    use tokio::io::{AsyncWriteExt, DuplexStream};
+
+    fn device() -> (AsyncDevice, DuplexStream) {
+        let (host, display) = tokio::io::duplex(256);
+        (AsyncDevice::with_transport(Box::new(host)), display)
+    }
+
+    async fn read_packet(display: &mut DuplexStream) -> Packet {
+        PacketCodec::new(display).read_packet_async().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn transact_buffers_reports() {
+        let (mut device, mut display) = device();
+        let responder = tokio::spawn(async move {
+            let packet = read_packet(&mut display).await;
+            assert_eq!(packet, Packet::new(0x1f, b"\x00\x01hi"));
+            display
+                .write_all(&Packet::new(0x80, &[5]).to_bytes())
+                .await
+                .unwrap();
+            display
+                .write_all(&Packet::new(0x5f, &[]).to_bytes())
+                .await
+                .unwrap();
+            display
+        });
+        device.set_text(1, 0, b"hi").await.unwrap();
+        let _display = responder.await.unwrap();
+        assert_eq!(
+            device.poll_report().await.unwrap(),
+            Some(Report::KeyActivity {
+                key: Key::Enter,
+                pressed: true
+            })
+        );
+        assert_eq!(device.poll_report().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn error_response() {
+        let (mut device, mut display) = device();
+        display
+            .write_all(&Packet::new(0xc6, &[]).to_bytes())
+            .await
+            .unwrap();
+        assert!(matches!(
+            device.clear_screen().await,
+            Err(Error::ReturnedError { command: 0x06, .. })
+        ));
+        assert_eq!(read_packet(&mut display).await, Packet::new(0x06, &[]));
+    }
+
+    #[tokio::test]
+    async fn partial_packet_survives_timeout() {
+        let (mut device, mut display) = device();
+        let report = Packet::new(0x80, &[5]).to_bytes();
+        display.write_all(&report[..3]).await.unwrap();
+        assert_eq!(
+            device
+                .wait_report(Some(Duration::from_millis(10)))
+                .await
+                .unwrap(),
+            None
+        );
+        display.write_all(&report[3..]).await.unwrap();
+        assert_eq!(
+            device.poll_report().await.unwrap(),
+            Some(Report::KeyActivity {
+                key: Key::Enter,
+                pressed: true
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn resync_after_corrupted_response() {
+        let (mut device, mut display) = device();
+        let mut corrupted = Packet::new(0x46, &[]).to_bytes();
+        corrupted[2] ^= 0x01;
+        display.write_all(&corrupted).await.unwrap();
+        display
+            .write_all(&Packet::new(0x46, &[]).to_bytes())
+            .await
+            .unwrap();
+        device.clear_screen().await.unwrap();
+        assert_eq!(read_packet(&mut display).await, Packet::new(0x06, &[]));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{CursorStyle, Device, Error, IntoLed, LedColor};
+
+/// Commands queued for sending with [`Device::batch`].
+///
+/// Each method sends the command right away, like the [`Device`] method of
+/// the same name, but does not wait for the response. All responses are read
+/// after the batch is finished. Errors returned by these methods are from
+/// checking the arguments or writing to the port; errors reported by the
+/// device are returned by [`Device::batch`].
+pub struct Batch<'a> {
+    pub(crate) device: &'a mut Device,
+}
+
+impl Batch<'_> {
+    /// Queues [`Device::clear_screen`].
+    pub fn clear_screen(&mut self) -> Result<(), Error> {
+        self.device.clear_screen()
+    }
+
+    /// Queues [`Device::set_text`].
+    pub fn set_text(&mut self, row: u8, col: u8, text: &[u8]) -> Result<(), Error> {
+        self.device.set_text(row, col, text)
+    }
+
+    /// Queues [`Device::set_cursor_position`].
+    pub fn set_cursor_position(&mut self, row: u8, col: u8) -> Result<(), Error> {
+        self.device.set_cursor_position(row, col)
+    }
+
+    /// Queues [`Device::set_cursor_style`].
+    pub fn set_cursor_style(&mut self, style: CursorStyle) -> Result<(), Error> {
+        self.device.set_cursor_style(style)
+    }
+
+    /// Queues [`Device::set_contrast`].
+    pub fn set_contrast(&mut self, contrast: u8) -> Result<(), Error> {
+        self.device.set_contrast(contrast)
+    }
+
+    /// Queues [`Device::set_backlight`].
+    ///
+    /// Unlike the `Device` method, both values must be given, since a batch
+    /// cannot read the current values from the device.
+    pub fn set_backlight(&mut self, screen: u8, keypad: u8) -> Result<(), Error> {
+        self.device.set_backlight(screen, keypad)
+    }
+
+    /// Queues [`Device::set_led`].
+    pub fn set_led(&mut self, index: impl IntoLed, red: u8, green: u8) -> Result<(), Error> {
+        self.device.set_led(index, red, green)
+    }
+
+    /// Queues [`Device::set_led_color`].
+    pub fn set_led_color(&mut self, index: impl IntoLed, color: LedColor) -> Result<(), Error> {
+        self.device.set_led_color(index, color)
+    }
+
+    /// Queues [`Device::set_gpio`].
+    pub fn set_gpio(&mut self, index: u8, value: u8) -> Result<(), Error> {
+        self.device.set_gpio(index, value)
+    }
+}
@@ -0,0 +1,65 @@
+use crate::charset::GlyphBitmap;
+use crate::limits::CGRAM_SLOTS;
+use crate::{KeyReporting, Screen};
+
+/// The expected boot state of a device, checked by
+/// [`Device::verify_boot_state`](crate::Device::verify_boot_state).
+///
+/// Every field is optional; fields that are `None` are not checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BootState {
+    /// The characters shown on the screen.
+    pub screen: Option<Screen>,
+
+    /// The LCD contrast.
+    pub contrast: Option<u8>,
+
+    /// The screen backlight brightness.
+    pub backlight: Option<u8>,
+
+    /// The keypad backlight brightness.
+    pub keypad_backlight: Option<u8>,
+
+    /// Which key events are reported. The order of the keys does not matter.
+    pub key_reporting: Option<KeyReporting>,
+
+    /// The custom character bitmaps, indexed by slot. Only the pixel bits of
+    /// each row are compared (see
+    /// [`Device::read_custom_character`](crate::Device::read_custom_character)).
+    pub custom_characters: [Option<GlyphBitmap>; CGRAM_SLOTS as usize],
+}
+
+/// A difference between the expected and actual state of a device, as
+/// returned by [`Device::verify_boot_state`](crate::Device::verify_boot_state).
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum BootStateMismatch {
+    Screen {
+        expected: Screen,
+        actual: Screen,
+    },
+    Contrast {
+        expected: u8,
+        actual: u8,
+    },
+    Backlight {
+        expected: u8,
+        actual: u8,
+    },
+    /// `actual` is `None` if the firmware does not report the keypad
+    /// backlight.
+    KeypadBacklight {
+        expected: u8,
+        actual: Option<u8>,
+    },
+    KeyReporting {
+        expected: KeyReporting,
+        actual: KeyReporting,
+    },
+    /// `expected` and `actual` only have the pixel bits set.
+    CustomCharacter {
+        index: u8,
+        expected: GlyphBitmap,
+        actual: GlyphBitmap,
+    },
+}
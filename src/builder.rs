@@ -0,0 +1,382 @@
+use crate::{
+    check_bounds, retry, Device, Error, Model, ReportOverflow, DEFAULT_BAUD_RATE,
+    DEFAULT_READ_TIMEOUT, DEFAULT_RETRY_BACKOFF, MAX_READ_TIMEOUT,
+};
+use std::time::Duration;
+
+/// Options for connecting to a device, created with [`Device::builder`].
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::Device;
+/// use std::time::Duration;
+///
+/// let builder = Device::builder("/dev/ttyACM0")
+///     .baud(19200)
+///     .timeout(Duration::from_secs(1));
+/// let device = builder.open()?;
+///
+/// // The builder can be reused, e.g. to reconnect later.
+/// drop(device);
+/// let device = builder.open()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceBuilder {
+    path: String,
+    baud_rate: u32,
+    timeout: Duration,
+    report_buffer_capacity: usize,
+    report_buffer_limit: Option<(usize, ReportOverflow)>,
+    retries: u8,
+    retry_backoff: Duration,
+    check_crc: bool,
+    model: Model,
+    detect_model: bool,
+}
+
+impl DeviceBuilder {
+    pub(crate) fn new(path: String) -> Self {
+        Self {
+            path,
+            baud_rate: DEFAULT_BAUD_RATE,
+            timeout: DEFAULT_READ_TIMEOUT,
+            report_buffer_capacity: 0,
+            report_buffer_limit: None,
+            retries: 0,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            check_crc: false,
+            model: Model::default(),
+            detect_model: false,
+        }
+    }
+
+    /// Sets the baud rate of the serial port.
+    ///
+    /// This only matters for the RS232 versions of the display, where it is
+    /// configurable (19200 or 115200). The USB version ignores it. Defaults to
+    /// [`DEFAULT_BAUD_RATE`].
+    pub fn baud(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Sets the read timeout of the serial port.
+    ///
+    /// This limits how long a single read waits for data. If the device is
+    /// connected through something with occasional latency spikes (like a
+    /// busy USB hub), a longer timeout avoids spurious `Io` timeout errors.
+    /// Defaults to [`DEFAULT_READ_TIMEOUT`].
+    ///
+    /// Recommended values:
+    ///
+    /// - 10 to 50 ms for tight polling loops (e.g. [`Device::poll_report`]),
+    ///   where waiting for input should not hold up the rest of the loop.
+    ///
+    /// - 100 to 500 ms for ordinary commands. Responses normally arrive within
+    ///   a few milliseconds, so this only matters on slow links.
+    ///
+    /// - 1 to 2 seconds if the device writes to its flash memory, as with
+    ///   [`Device::save_boot_state`], which takes noticeably longer to
+    ///   acknowledge.
+    ///
+    /// The timeout must be at least 1 millisecond and at most
+    /// [`MAX_READ_TIMEOUT`]; otherwise [`DeviceBuilder::open`] fails. A zero
+    /// timeout would make every read fail immediately.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many reports to allocate space for in the report buffer up
+    /// front.
+    ///
+    /// Reports that arrive while waiting for a command's response are
+    /// buffered until they are polled. Preallocating avoids reallocations if
+    /// many reports are expected; the buffer still grows beyond this if
+    /// needed, up to the limit set with
+    /// [`DeviceBuilder::report_buffer_limit`]. Defaults to 0.
+    pub fn report_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.report_buffer_capacity = capacity;
+        self
+    }
+
+    /// Limits how many reports are buffered, and chooses which reports to
+    /// drop when the limit is reached.
+    ///
+    /// See [`Device::set_report_buffer_limit`]. Defaults to no limit.
+    pub fn report_buffer_limit(mut self, limit: usize, overflow: ReportOverflow) -> Self {
+        self.report_buffer_limit = Some((limit, overflow));
+        self
+    }
+
+    /// Sets how many times a command is retried if its response is invalid.
+    ///
+    /// See [`Device::set_retries`]. Defaults to 0.
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets how long to wait before retrying a command for the first time;
+    /// later retries wait twice as long as the one before.
+    ///
+    /// Defaults to 10 milliseconds.
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Sets whether to check that the device uses the same packet CRC
+    /// algorithm as this crate when connecting.
+    ///
+    /// See [`Device::check_crc_compatibility`]. This costs a ping, and turns
+    /// the timeouts that a mismatch would otherwise cause into an immediate
+    /// `CrcAlgorithmMismatch` error from [`DeviceBuilder::open`]. Defaults to
+    /// `false`.
+    pub fn check_crc(mut self, check_crc: bool) -> Self {
+        self.check_crc = check_crc;
+        self
+    }
+
+    /// Sets the model of the device, which decides the GPIO pins used for
+    /// its indicator LEDs.
+    ///
+    /// See [`Model`] for the supported models and their LED tables. Defaults
+    /// to [`Model::Cfa635`].
+    pub fn model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Sets whether to detect the model of the device when connecting.
+    ///
+    /// If set, [`DeviceBuilder::open`] reads the device's version (see
+    /// [`Device::version`]) and uses the model it reports instead of the one
+    /// set with [`DeviceBuilder::model`]. That model is still used if the
+    /// reported one is not supported. Defaults to `false`.
+    pub fn detect_model(mut self, detect_model: bool) -> Self {
+        self.detect_model = detect_model;
+        self
+    }
+
+    /// Opens the serial port and connects to the device.
+    ///
+    /// Any input already waiting on the port, such as the rest of a response
+    /// from a previous session, is discarded (see [`Device::flush_input`]).
+    ///
+    /// The builder is not consumed, so it can be used again to reconnect.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the timeout is shorter than 1 millisecond or
+    ///   longer than [`MAX_READ_TIMEOUT`]. The error's `field` is `"timeout"`,
+    ///   and its `value`, `min` and `max` are in milliseconds. This is checked
+    ///   before the port is opened.
+    ///
+    /// - `CrcAlgorithmMismatch` or `InvalidRead` - If
+    ///   [`DeviceBuilder::check_crc`] is set and the check fails (see
+    ///   [`Device::check_crc_compatibility`]).
+    ///
+    /// - `InvalidRead` - If [`DeviceBuilder::detect_model`] is set and the
+    ///   version response cannot be parsed.
+    pub fn open(&self) -> Result<Device, Error> {
+        self.check_timeout()?;
+        let port = serialport::new(&self.path, self.baud_rate)
+            .timeout(self.timeout)
+            .open()?;
+        self.configure(Device::from_serial_port(port))
+    }
+
+    /// Checks that the timeout is within the range documented on
+    /// [`DeviceBuilder::timeout`].
+    fn check_timeout(&self) -> Result<(), Error> {
+        check_bounds(
+            "timeout",
+            self.timeout.as_millis().try_into().unwrap_or(usize::MAX),
+            1,
+            MAX_READ_TIMEOUT.as_millis() as usize,
+        )
+    }
+
+    /// Applies the options that do not concern the serial port itself to a
+    /// newly connected device.
+    fn configure(&self, mut device: Device) -> Result<Device, Error> {
+        device.report_buffer.reserve(self.report_buffer_capacity);
+        if let Some((limit, overflow)) = self.report_buffer_limit {
+            device.set_report_buffer_limit(limit, overflow);
+        }
+        device.set_retries(self.retries, self.retry_backoff);
+        device.model = self.model;
+        device.flush_input()?;
+        if self.check_crc {
+            device.check_crc_compatibility()?;
+        }
+        if self.detect_model {
+            let version = device.version()?;
+            match Model::from_name(&version.model) {
+                Some(model) => device.model = model,
+                None => log::warn!(
+                    "unsupported model {:?}, assuming {}",
+                    version.model,
+                    self.model
+                ),
+            }
+        }
+        Ok(device)
+    }
+
+    /// Like [`DeviceBuilder::open`], but retries if the port cannot be opened.
+    ///
+    /// See [`Device::open_with_retry`] for how `attempts` and `backoff` are
+    /// used. An invalid timeout is reported right away, without retrying.
+    pub fn open_with_retry(&self, attempts: u32, backoff: Duration) -> Result<Device, Error> {
+        self.check_timeout()?;
+        retry(attempts, backoff, || self.open())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Packet;
+    use crate::mock;
+
+    #[test]
+    fn builder_options() {
+        let builder = Device::builder("/dev/does-not-exist")
+            .baud(19200)
+            .timeout(Duration::from_secs(1))
+            .report_buffer_capacity(64)
+            .report_buffer_limit(128, ReportOverflow::DropNewest)
+            .retries(2)
+            .retry_backoff(Duration::from_millis(5))
+            .check_crc(true)
+            .model(Model::Cfa633)
+            .detect_model(true);
+        assert_eq!(builder.path, "/dev/does-not-exist");
+        assert_eq!(builder.baud_rate, 19200);
+        assert_eq!(builder.timeout, Duration::from_secs(1));
+        assert_eq!(builder.report_buffer_capacity, 64);
+        assert_eq!(
+            builder.report_buffer_limit,
+            Some((128, ReportOverflow::DropNewest))
+        );
+        assert_eq!(builder.retries, 2);
+        assert_eq!(builder.retry_backoff, Duration::from_millis(5));
+        assert!(builder.check_crc);
+        assert_eq!(builder.model, Model::Cfa633);
+        assert!(builder.detect_model);
+
+        let reused = builder.clone();
+        assert!(matches!(builder.open(), Err(Error::SerialPort(_))));
+        assert!(matches!(reused.open(), Err(Error::SerialPort(_))));
+    }
+
+    #[test]
+    fn check_crc_on_open() {
+        let builder = Device::builder("/dev/does-not-exist");
+        let (device, mock) = mock::device();
+        builder.configure(device).unwrap();
+        assert_eq!(mock.sent(), []);
+
+        let builder = builder.check_crc(true);
+        let (device, mock) = mock::device();
+        builder.configure(device).unwrap();
+        assert_eq!(mock.sent().len(), 1);
+        assert_eq!(mock.sent()[0].packet_type(), 0x00);
+
+        let mut bad_crc = Packet::new(0x40, b"crc probe").to_bytes();
+        *bad_crc.last_mut().unwrap() ^= 0xff;
+        let (device, mock) = mock::device();
+        for _ in 0..3 {
+            mock.respond_bytes(0x00, bad_crc.clone());
+        }
+        assert!(matches!(
+            builder.configure(device),
+            Err(Error::CrcAlgorithmMismatch)
+        ));
+    }
+
+    #[test]
+    fn model_selects_led_pins() {
+        let (device, mock) = mock::device();
+        let mut device = Device::builder("/dev/does-not-exist")
+            .configure(device)
+            .unwrap();
+        assert_eq!(device.model(), Model::Cfa635);
+        device.set_led(1, 100, 0).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(0x22, &[10, 100]), Packet::new(0x22, &[9, 0])]
+        );
+
+        let (device, mock) = mock::device();
+        let mut device = Device::builder("/dev/does-not-exist")
+            .model(Model::Cfa633)
+            .configure(device)
+            .unwrap();
+        assert_eq!(device.model(), Model::Cfa633);
+        assert!(matches!(
+            device.set_led(1, 100, 0),
+            Err(Error::UnsupportedModel(Model::Cfa633))
+        ));
+        assert_eq!(mock.sent(), []);
+    }
+
+    #[test]
+    fn detect_model_on_open() {
+        let builder = Device::builder("/dev/does-not-exist").detect_model(true);
+        let (device, mock) = mock::device();
+        mock.respond(0x01, b"CFA633:h1.0,f1.0");
+        let device = builder.configure(device).unwrap();
+        assert_eq!(device.model(), Model::Cfa633);
+        assert_eq!(mock.sent(), [Packet::new(0x01, &[])]);
+
+        // An unsupported model falls back to the configured one.
+        let (device, mock) = mock::device();
+        mock.respond(0x01, b"CFA631:h1.0,f1.0");
+        let device = builder.configure(device).unwrap();
+        assert_eq!(device.model(), Model::Cfa635);
+
+        let (device, mock) = mock::device();
+        mock.respond(0x01, b"garbage");
+        assert!(matches!(builder.configure(device), Err(Error::InvalidRead)));
+    }
+
+    #[test]
+    fn timeout_is_validated() {
+        for (timeout, millis) in [
+            (Duration::ZERO, 0),
+            (Duration::from_micros(999), 0),
+            (Duration::from_secs(61), 61_000),
+            (Duration::MAX, usize::MAX),
+        ] {
+            let result = Device::builder("/dev/does-not-exist")
+                .timeout(timeout)
+                .open();
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::InvalidArgument {
+                        field: "timeout",
+                        value,
+                        min: 1,
+                        max: 60_000,
+                    }) if value == millis
+                ),
+                "{:?}",
+                timeout
+            );
+        }
+
+        // Valid timeouts get as far as opening the port.
+        for timeout in [Duration::from_millis(1), MAX_READ_TIMEOUT] {
+            let result = Device::builder("/dev/does-not-exist")
+                .timeout(timeout)
+                .open();
+            assert!(matches!(result, Err(Error::SerialPort(_))));
+        }
+    }
+}
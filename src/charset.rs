@@ -0,0 +1,278 @@
+//! Utilities for working with the display's character set.
+
+use crate::limits::{CGRAM_SLOTS, CUSTOM_CHAR_HEIGHT, CUSTOM_CHAR_WIDTH};
+
+/// The byte substituted by [`encode`] for characters that the display cannot
+/// show.
+pub const DEFAULT_PLACEHOLDER: u8 = b'?';
+
+/// Returns the character ROM code for `c`, or `None` if the display has no
+/// equivalent glyph.
+///
+/// The ASCII characters whose ROM codes match ASCII are mapped: letters,
+/// digits, space, and the punctuation `!"#%&'()*+,-./:;<=>?`. The other
+/// printable ASCII codes (like `$`, `@` and `[`) show different glyphs on this
+/// display.
+///
+/// A few symbols from elsewhere in the character ROM are also mapped: the
+/// arrows `↑↓→←` (`0x18..=0x1b`), the degree sign `°` (`0xb0`), and the micro
+/// sign `µ` (`0xb5`, also used for the Greek letter `μ`).
+pub fn encode_char(c: char) -> Option<u8> {
+    renderable().find(|&(r, _)| r == c).map(|(_, code)| code)
+}
+
+/// ASCII characters that the display can show, in character ROM order.
+///
+/// Each of these has the same code in the ROM as in ASCII.
+const RENDERABLE: &str = " !\"#%&'()*+,-./0123456789:;<=>?\
+    ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Arrows, which come before the ASCII characters in the ROM.
+const ARROWS: [(char, u8); 4] = [('↑', 0x18), ('↓', 0x19), ('→', 0x1a), ('←', 0x1b)];
+
+/// Symbols from the upper half of the ROM.
+const SYMBOLS: [(char, u8); 3] = [('°', 0xb0), ('µ', 0xb5), ('μ', 0xb5)];
+
+/// Returns every character that [`encode_char`] can map, along with its
+/// character ROM code, in character ROM order.
+pub fn renderable() -> impl Iterator<Item = (char, u8)> {
+    ARROWS
+        .into_iter()
+        .chain(RENDERABLE.chars().map(|c| (c, c as u8)))
+        .chain(SYMBOLS)
+}
+
+/// Encodes `text` into character ROM codes, replacing characters the display
+/// cannot show with [`DEFAULT_PLACEHOLDER`] (`?`).
+///
+/// Use [`encode_with`] to choose a different placeholder or to find out which
+/// characters were replaced.
+pub fn encode(text: &str) -> Vec<u8> {
+    encode_with(text, DEFAULT_PLACEHOLDER).bytes
+}
+
+/// Encodes `text` into character ROM codes, replacing characters the display
+/// cannot show with `placeholder`.
+///
+/// `placeholder` is used as-is, so it may also be a custom character slot.
+pub fn encode_with(text: &str, placeholder: u8) -> Encoded {
+    let mut encoded = Encoded {
+        bytes: Vec::with_capacity(text.len()),
+        unmappable: Vec::new(),
+    };
+    for c in text.chars() {
+        match encode_char(c) {
+            Some(code) => encoded.bytes.push(code),
+            None => {
+                encoded.bytes.push(placeholder);
+                encoded.unmappable.push(c);
+            }
+        }
+    }
+    encoded
+}
+
+/// Replaces control bytes in `text` with spaces, leaving custom characters
+/// intact.
+///
+/// Bytes `0x08..0x20` (which include tab, newline and carriage return) show
+/// unrelated glyphs from the character ROM, which is rarely what was intended.
+/// Bytes `0..CGRAM_SLOTS` select custom characters and are kept.
+///
+/// [`CGRAM_SLOTS`]: crate::limits::CGRAM_SLOTS
+pub fn replace_control_bytes(text: &[u8]) -> Vec<u8> {
+    text.iter()
+        .map(|&byte| {
+            if (CGRAM_SLOTS..0x20).contains(&byte) {
+                b' '
+            } else {
+                byte
+            }
+        })
+        .collect()
+}
+
+/// The result of [`encode_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoded {
+    /// The encoded text, one byte per character of the input.
+    pub bytes: Vec<u8>,
+
+    /// The characters that were replaced by the placeholder, in order of
+    /// appearance.
+    pub unmappable: Vec<char>,
+}
+
+/// The pixel grid of a custom character, indexed as `grid[row][col]`, with
+/// `true` for pixels that are on.
+pub type GlyphGrid = [[bool; CUSTOM_CHAR_WIDTH as usize]; CUSTOM_CHAR_HEIGHT as usize];
+
+/// The bitmap of a custom character: one byte per row, top to bottom.
+pub type GlyphBitmap = [u8; CUSTOM_CHAR_HEIGHT as usize];
+
+/// Converts a grid of pixels into a custom character bitmap.
+///
+/// Each row of the grid becomes one byte, with the leftmost pixel in the most
+/// significant used bit (bit 5) and the rightmost pixel in bit 0.
+pub const fn glyph_from_grid(grid: GlyphGrid) -> GlyphBitmap {
+    let mut bitmap = [0; CUSTOM_CHAR_HEIGHT as usize];
+    let mut row = 0;
+    while row < grid.len() {
+        let mut col = 0;
+        while col < grid[row].len() {
+            if grid[row][col] {
+                bitmap[row] |= 1 << (CUSTOM_CHAR_WIDTH as usize - 1 - col);
+            }
+            col += 1;
+        }
+        row += 1;
+    }
+    bitmap
+}
+
+/// Converts ASCII art into a custom character bitmap.
+///
+/// Each string is one row of pixels, and must be exactly
+/// [`CUSTOM_CHAR_WIDTH`] characters long. `#` marks a pixel that is on, and
+/// `.` or a space marks a pixel that is off.
+///
+/// This is usually used through the [`glyph!`](crate::glyph) macro, which
+/// evaluates it at compile time.
+///
+/// # Panics
+///
+/// If a row has the wrong length or contains any other character.
+pub const fn glyph_from_art(rows: [&str; CUSTOM_CHAR_HEIGHT as usize]) -> GlyphBitmap {
+    let mut grid = [[false; CUSTOM_CHAR_WIDTH as usize]; CUSTOM_CHAR_HEIGHT as usize];
+    let mut row = 0;
+    while row < rows.len() {
+        let bytes = rows[row].as_bytes();
+        assert!(
+            bytes.len() == CUSTOM_CHAR_WIDTH as usize,
+            "glyph row has the wrong length"
+        );
+        let mut col = 0;
+        while col < bytes.len() {
+            grid[row][col] = match bytes[col] {
+                b'#' => true,
+                b'.' | b' ' => false,
+                _ => panic!("glyph row contains an invalid character"),
+            };
+            col += 1;
+        }
+        row += 1;
+    }
+    glyph_from_grid(grid)
+}
+
+/// Defines a custom character bitmap using ASCII art, checked at compile time.
+///
+/// See [`charset::glyph_from_art`](crate::charset::glyph_from_art) for the
+/// format.
+///
+/// ```
+/// const ARROW_UP: [u8; 8] = cfa635::glyph![
+///     "..#...",
+///     ".###..",
+///     "#.#.#.",
+///     "..#...",
+///     "..#...",
+///     "..#...",
+///     "..#...",
+///     "......",
+/// ];
+/// assert_eq!(ARROW_UP, [0x08, 0x1c, 0x2a, 0x08, 0x08, 0x08, 0x08, 0x00]);
+/// ```
+#[macro_export]
+macro_rules! glyph {
+    ($($row:expr),* $(,)?) => {{
+        const GLYPH: $crate::charset::GlyphBitmap = $crate::charset::glyph_from_art([$($row),*]);
+        GLYPH
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_ascii() {
+        assert_eq!(encode("Hello, world!"), b"Hello, world!");
+        assert_eq!(encode("50% (a+b)/2"), b"50% (a+b)/2");
+        assert_eq!(encode("$5 @ [x]"), b"?5 ? ?x?");
+    }
+
+    #[test]
+    fn renderable_table() {
+        assert_eq!(renderable().count(), 90);
+        assert_eq!(renderable().next(), Some(('↑', 0x18)));
+        assert!(renderable().any(|m| m == (' ', 0x20)));
+        assert!(renderable().any(|m| m == ('A', 0x41)));
+        assert!(renderable().any(|m| m == ('?', 0x3f)));
+        assert!(renderable().all(|(c, _)| c != '$' && c != '@'));
+        assert!(renderable().all(|(c, code)| encode_char(c) == Some(code)));
+        assert!(renderable().is_sorted_by_key(|(_, code)| code));
+    }
+
+    #[test]
+    fn encode_symbols() {
+        assert_eq!(encode_char('↑'), Some(0x18));
+        assert_eq!(encode_char('↓'), Some(0x19));
+        assert_eq!(encode_char('→'), Some(0x1a));
+        assert_eq!(encode_char('←'), Some(0x1b));
+        assert_eq!(encode_char('°'), Some(0xb0));
+        assert_eq!(encode_char('µ'), Some(0xb5));
+        assert_eq!(encode_char('μ'), Some(0xb5));
+        assert_eq!(encode("25°C"), b"25\xb0C");
+    }
+
+    #[test]
+    fn encode_placeholder() {
+        assert_eq!(encode("café 🙂"), b"caf? ?");
+        let encoded = encode_with("naïve 🙂!", b'_');
+        assert_eq!(encoded.bytes, b"na_ve _!");
+        assert_eq!(encoded.unmappable, ['ï', '🙂']);
+        assert!(encode_with("plain", b'_').unmappable.is_empty());
+    }
+
+    #[test]
+    fn control_bytes() {
+        assert_eq!(replace_control_bytes(b"a\tb\r\n"), b"a b  ");
+        assert_eq!(replace_control_bytes(b"\x00\x07\x08\x1f "), b"\x00\x07   ");
+        assert_eq!(replace_control_bytes(b"plain"), b"plain");
+    }
+
+    #[test]
+    fn arrow_from_grid() {
+        const O: bool = false;
+        const X: bool = true;
+        #[rustfmt::skip]
+        let grid = [
+            [O, O, O, X, O, O],
+            [O, O, O, O, X, O],
+            [X, X, X, X, X, X],
+            [O, O, O, O, X, O],
+            [O, O, O, X, O, O],
+            [O, O, O, O, O, O],
+            [O, O, O, O, O, O],
+            [O, O, O, O, O, O],
+        ];
+        assert_eq!(
+            glyph_from_grid(grid),
+            [0x04, 0x02, 0x3f, 0x02, 0x04, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn arrow_from_art() {
+        let bitmap =
+            glyph!["...#..", "....#.", "######", "....#.", "...#..", "      ", "      ", "      ",];
+        assert_eq!(bitmap, [0x04, 0x02, 0x3f, 0x02, 0x04, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn art_wrong_width() {
+        glyph_from_art(["#####", "", "", "", "", "", "", ""]);
+    }
+}
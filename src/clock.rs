@@ -0,0 +1,26 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The time source of a [`Device`](crate::Device), for the operations that
+/// measure or wait for a period of time.
+///
+/// Tests replace it with a virtual clock, so that timings can be checked
+/// exactly and delays do not slow the tests down.
+pub(crate) trait Clock: Send {
+    fn now(&self) -> Instant;
+
+    fn sleep(&self, duration: Duration);
+}
+
+/// The real clock, using [`Instant::now`] and [`thread::sleep`].
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
@@ -1,8 +1,19 @@
+//! Low-level encoding and decoding of CFA635 packets.
+//!
+//! Most users will not need this module; [`Device`](crate::Device) takes care
+//! of framing commands and routing responses. It is mainly useful for tooling
+//! that works with captured serial traffic.
+
+use std::collections::VecDeque;
 use std::fmt;
-use std::io::{Read, Write};
+use std::io::{self, ErrorKind, Read, Write};
 
 use thiserror::Error;
 
+/// The maximum length of a packet's data payload.
+///
+/// Also available as [`limits::MAX_DATA_LEN`](crate::limits::MAX_DATA_LEN),
+/// along with the other protocol limits.
 pub const MAX_DATA_LEN: usize = 22;
 
 #[derive(Debug, Error)]
@@ -20,6 +31,9 @@ pub enum ReadPacketError {
 pub enum WritePacketError {
     #[error("io error")]
     Io(#[from] std::io::Error),
+
+    #[error("packet data is {len} bytes long, the maximum is {MAX_DATA_LEN}")]
+    InvalidLength { len: usize },
 }
 
 #[derive(Clone)]
@@ -32,8 +46,31 @@ pub struct Packet {
 }
 
 impl Packet {
+    /// Creates a packet with the given type and data, and calculates its CRC.
+    ///
+    /// # Panics
+    ///
+    /// If `data` is longer than [`MAX_DATA_LEN`]. The methods of
+    /// [`Device`](crate::Device) check their arguments before building
+    /// packets, so this only happens if a packet is built directly. Use
+    /// [`Packet::try_new`] when the length of `data` is not known to be in
+    /// range.
     pub fn new(packet_type: u8, data: &[u8]) -> Self {
-        assert!(data.len() <= MAX_DATA_LEN, "packet data is too large");
+        match Self::try_new(packet_type, data) {
+            Ok(packet) => packet,
+            Err(_) => panic!("packet data is too large"),
+        }
+    }
+
+    /// Like [`Packet::new`], but returns an error instead of panicking.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidLength` - If `data` is longer than [`MAX_DATA_LEN`].
+    pub fn try_new(packet_type: u8, data: &[u8]) -> Result<Self, WritePacketError> {
+        if data.len() > MAX_DATA_LEN {
+            return Err(WritePacketError::InvalidLength { len: data.len() });
+        }
         let mut packet = Self {
             packet_type,
             data_len: data.len() as u8,
@@ -43,7 +80,35 @@ impl Packet {
         };
         packet.data_array[..data.len()].copy_from_slice(data);
         packet.set_crc();
-        packet
+        Ok(packet)
+    }
+
+    /// Parses a complete packet from its on-wire byte representation.
+    ///
+    /// # Errors
+    ///
+    /// - `Io` - If the bytes end before the end of the packet.
+    ///
+    /// - `InvalidPacket` - If the data length is out of range, if there are
+    ///   extra bytes after the end of the packet, or if the CRC does not match
+    ///   the packet's contents.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadPacketError> {
+        let mut reader = bytes;
+        let packet = PacketCodec::new(&mut reader).read_packet()?;
+        if !reader.is_empty() || !packet.check_crc() {
+            return Err(ReadPacketError::InvalidPacket);
+        }
+        Ok(packet)
+    }
+
+    /// Returns the on-wire byte representation of the packet, as written by
+    /// [`PacketCodec::write_packet`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.data().len());
+        bytes.extend_from_slice(&[self.packet_type, self.data_len]);
+        bytes.extend_from_slice(self.data());
+        bytes.extend_from_slice(&self.crc());
+        bytes
     }
 
     pub fn packet_type(&self) -> u8 {
@@ -65,6 +130,11 @@ impl Packet {
 
     /// Algorithm to calculate the CRC for the given packet data.
     ///
+    /// This is the CRC-16/X-25 algorithm (reflected polynomial `0x8408`,
+    /// initial value `0xFFFF`, final XOR `0xFFFF`). The result is returned in
+    /// little-endian byte order, which is also the order the bytes are sent
+    /// on the wire.
+    ///
     /// Adapted from [`get_crc` in cfa_linux_examples](https://github.com/crystalfontz/cfa_linux_examples/blob/b07028f2c26c1fb9ae933d07508936bebead4067/include/cf_packet.c)
     pub(crate) fn calculate_crc(&self) -> [u8; 2] {
         #[rustfmt::skip]
@@ -130,6 +200,15 @@ impl Packet {
     }
 }
 
+impl TryFrom<(u8, &[u8])> for Packet {
+    type Error = WritePacketError;
+
+    /// Builds a packet from its type and data; see [`Packet::try_new`].
+    fn try_from((packet_type, data): (u8, &[u8])) -> Result<Self, Self::Error> {
+        Self::try_new(packet_type, data)
+    }
+}
+
 impl fmt::Debug for Packet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Packet")
@@ -148,50 +227,189 @@ impl PartialEq for Packet {
 
 pub struct PacketCodec<T> {
     inner: T,
+    /// Bytes that have been read from `inner` but not consumed yet.
+    buffer: VecDeque<u8>,
+    /// Bytes skipped by `read_packet_resync` since it last returned a packet.
+    skipped: usize,
 }
 
 impl<T> PacketCodec<T> {
     pub fn new(inner: T) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            buffer: VecDeque::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Returns the number of bytes that have been read from the stream but not
+    /// consumed yet, e.g. the start of a packet whose read timed out.
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the number of bytes that [`PacketCodec::read_packet_resync`]
+    /// has skipped without finding a valid packet yet, e.g. because a
+    /// corrupted packet was followed by a read timeout.
+    pub fn skipped_len(&self) -> usize {
+        self.skipped
     }
 
     pub fn inner(&self) -> &T {
         &self.inner
     }
+
+    pub(crate) fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Discards the buffered bytes (see [`PacketCodec::buffered_len`]),
+    /// returning how many there were. This also resets
+    /// [`PacketCodec::skipped_len`].
+    pub fn clear_buffer(&mut self) -> usize {
+        let len = self.buffer.len();
+        self.buffer.clear();
+        self.skipped = 0;
+        len
+    }
+
+    /// Returns the packet at the front of the buffer, which must hold at least
+    /// `4 + data_len` bytes.
+    fn buffered_packet(&self, data_len: usize) -> Packet {
+        let mut data_array = [0u8; MAX_DATA_LEN];
+        for (dst, &src) in data_array
+            .iter_mut()
+            .zip(self.buffer.range(2..2 + data_len))
+        {
+            *dst = src;
+        }
+        Packet {
+            packet_type: self.buffer[0],
+            data_len: data_len as u8,
+            data_array,
+            crc: [self.buffer[2 + data_len], self.buffer[3 + data_len]],
+            trusted_crc: false,
+        }
+    }
+
+    /// Takes the first packet with a valid CRC from the buffer, skipping any
+    /// bytes before it, as in [`PacketCodec::read_packet_resync`].
+    ///
+    /// If the buffer does not hold a complete packet yet, returns how many
+    /// bytes need to be buffered before trying again.
+    fn take_packet_resync(&mut self) -> Result<Packet, usize> {
+        loop {
+            if self.buffer.len() < 2 {
+                return Err(2);
+            }
+            let data_len = usize::from(self.buffer[1]);
+            if data_len <= MAX_DATA_LEN {
+                if self.buffer.len() < 4 + data_len {
+                    return Err(4 + data_len);
+                }
+                let packet = self.buffered_packet(data_len);
+                if packet.check_crc() {
+                    self.buffer.drain(..4 + data_len);
+                    return Ok(packet);
+                }
+            }
+            // Try framing a packet at the next byte instead.
+            self.buffer.pop_front();
+            self.skipped += 1;
+        }
+    }
 }
 
 impl<T> PacketCodec<T>
 where
     T: Read,
 {
+    /// Reads the next packet from the stream.
+    ///
+    /// If a read fails partway through a packet (e.g. because it timed out),
+    /// the bytes read so far are kept, and the next call continues the same
+    /// packet.
     pub fn read_packet(&mut self) -> Result<Packet, ReadPacketError> {
-        let mut packet_type = [0u8; 1];
-        self.inner.read_exact(&mut packet_type)?;
-        let packet_type = u8::from_le_bytes(packet_type);
-
-        let mut data_len = [0u8; 1];
-        self.inner.read_exact(&mut data_len)?;
-        let data_len = u8::from_le_bytes(data_len);
-        if data_len as usize > MAX_DATA_LEN {
+        self.fill_buffer(2)?;
+        let data_len = usize::from(self.buffer[1]);
+        if data_len > MAX_DATA_LEN {
+            self.buffer.drain(..2);
             return Err(ReadPacketError::InvalidPacket);
         }
+        self.fill_buffer(4 + data_len)?;
+        let packet = self.buffered_packet(data_len);
+        self.buffer.drain(..4 + data_len);
+        Ok(packet)
+    }
 
-        let mut data_array = [0u8; MAX_DATA_LEN];
-        self.inner
-            .read_exact(&mut data_array[..data_len as usize])?;
-
-        let mut crc = [0u8; 2];
-        self.inner.read_exact(&mut crc)?;
+    /// Like [`PacketCodec::read_packet`], but skips bytes until a packet with a
+    /// valid CRC is found, to recover from bytes that were lost or corrupted
+    /// on the wire. Returns the packet and the number of bytes skipped.
+    ///
+    /// Note: A packet with an unexpected CRC is indistinguishable from
+    /// corruption, so this should not be used to talk to a device that may use
+    /// a different CRC algorithm.
+    ///
+    /// If a read fails after bytes were skipped, the count carries over to
+    /// the next call, and can be checked with [`PacketCodec::skipped_len`].
+    pub fn read_packet_resync(&mut self) -> Result<(Packet, usize), ReadPacketError> {
+        loop {
+            match self.take_packet_resync() {
+                Ok(packet) => return Ok((packet, std::mem::take(&mut self.skipped))),
+                Err(needed) => self.fill_buffer(needed)?,
+            }
+        }
+    }
 
-        let packet = Packet {
-            packet_type,
-            data_len,
-            data_array,
-            crc,
-            trusted_crc: false,
-        };
+    /// Consumes the codec, returning an iterator over all packets in the
+    /// stream.
+    ///
+    /// The iterator ends when the stream reaches EOF on a packet boundary. If
+    /// an error occurs (including EOF in the middle of a packet), it is
+    /// yielded and the iterator ends.
+    pub fn packets(mut self) -> impl Iterator<Item = Result<Packet, ReadPacketError>> {
+        let mut done = false;
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            if self.buffer.is_empty() {
+                let mut packet_type = [0u8; 1];
+                loop {
+                    match self.inner.read(&mut packet_type) {
+                        Ok(0) => {
+                            done = true;
+                            return None;
+                        }
+                        Ok(_) => break self.buffer.extend(packet_type),
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(e) => {
+                            done = true;
+                            return Some(Err(e.into()));
+                        }
+                    }
+                }
+            }
+            let result = self.read_packet();
+            done = result.is_err();
+            Some(result)
+        })
+    }
 
-        Ok(packet)
+    /// Reads from the stream until at least `len` bytes are buffered.
+    fn fill_buffer(&mut self, len: usize) -> io::Result<()> {
+        let mut chunk = [0u8; 4 + MAX_DATA_LEN];
+        while self.buffer.len() < len {
+            // Only read what is needed, leaving the rest in the stream.
+            let want = len - self.buffer.len();
+            match self.inner.read(&mut chunk[..want]) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.buffer.extend(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     }
 }
 
@@ -209,9 +427,81 @@ where
     }
 }
 
+#[cfg(feature = "tokio")]
+impl<T> PacketCodec<T>
+where
+    T: tokio::io::AsyncRead + Unpin,
+{
+    /// Like [`PacketCodec::read_packet`], but for asynchronous streams.
+    ///
+    /// This is cancel-safe: if the future is dropped partway through a packet
+    /// (e.g. by a timeout), the bytes read so far are kept, and the next call
+    /// continues the same packet.
+    pub async fn read_packet_async(&mut self) -> Result<Packet, ReadPacketError> {
+        self.fill_buffer_async(2).await?;
+        let data_len = usize::from(self.buffer[1]);
+        if data_len > MAX_DATA_LEN {
+            self.buffer.drain(..2);
+            return Err(ReadPacketError::InvalidPacket);
+        }
+        self.fill_buffer_async(4 + data_len).await?;
+        let packet = self.buffered_packet(data_len);
+        self.buffer.drain(..4 + data_len);
+        Ok(packet)
+    }
+
+    /// Like [`PacketCodec::read_packet_resync`], but for asynchronous streams.
+    ///
+    /// This is cancel-safe in the same way as
+    /// [`PacketCodec::read_packet_async`], and the count of skipped bytes
+    /// carries over to the next call.
+    pub async fn read_packet_resync_async(&mut self) -> Result<(Packet, usize), ReadPacketError> {
+        loop {
+            match self.take_packet_resync() {
+                Ok(packet) => return Ok((packet, std::mem::take(&mut self.skipped))),
+                Err(needed) => self.fill_buffer_async(needed).await?,
+            }
+        }
+    }
+
+    /// Like [`PacketCodec::fill_buffer`], but for asynchronous streams.
+    ///
+    /// Each read is added to the buffer as soon as it completes, so no data
+    /// is lost if the future is dropped.
+    async fn fill_buffer_async(&mut self, len: usize) -> io::Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let mut chunk = [0u8; 4 + MAX_DATA_LEN];
+        while self.buffer.len() < len {
+            let want = len - self.buffer.len();
+            match self.inner.read(&mut chunk[..want]).await? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => self.buffer.extend(&chunk[..n]),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T> PacketCodec<T>
+where
+    T: tokio::io::AsyncWrite + Unpin,
+{
+    /// Like [`PacketCodec::write_packet`], but for asynchronous streams.
+    pub async fn write_packet_async(&mut self, packet: &Packet) -> Result<(), WritePacketError> {
+        use tokio::io::AsyncWriteExt;
+
+        self.inner.write_all(&packet.to_bytes()).await?;
+        self.inner.flush().await?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn cross_check_crc() {
@@ -244,6 +534,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn crc_wire_order() {
+        // Expected values are CRC-16/X-25 over the type, length and data
+        // bytes, transmitted low byte first.
+        let clear_screen = Packet::new(0x06, &[]);
+        assert_eq!(clear_screen.crc(), [0x97, 0x5b]);
+
+        let set_contrast = Packet::new(0x0d, &[120]);
+        assert_eq!(set_contrast.crc(), [0xa4, 0xdf]);
+
+        let mut buffer = Vec::new();
+        PacketCodec::new(&mut buffer)
+            .write_packet(&clear_screen)
+            .expect("write failed");
+        assert_eq!(buffer, [0x06, 0x00, 0x97, 0x5b]);
+    }
+
     #[test]
     fn packet_roundtrip() {
         let test_packet = Packet::new(0x00, b"Hello World");
@@ -258,4 +565,166 @@ mod tests {
         assert!(read_packet.check_crc());
         assert_eq!(read_packet, test_packet);
     }
+
+    #[test]
+    fn packet_from_bytes() {
+        let packet = Packet::from_bytes(&[0x06, 0x00, 0x97, 0x5b]).expect("parse failed");
+        assert_eq!(packet, Packet::new(0x06, &[]));
+
+        // Truncated
+        assert!(matches!(
+            Packet::from_bytes(&[0x06, 0x00, 0x97]),
+            Err(ReadPacketError::Io(_))
+        ));
+        // Corrupt CRC
+        assert!(matches!(
+            Packet::from_bytes(&[0x06, 0x00, 0x97, 0x5c]),
+            Err(ReadPacketError::InvalidPacket)
+        ));
+        // Trailing bytes
+        assert!(matches!(
+            Packet::from_bytes(&[0x06, 0x00, 0x97, 0x5b, 0x00]),
+            Err(ReadPacketError::InvalidPacket)
+        ));
+        // Data length out of range
+        assert!(matches!(
+            Packet::from_bytes(&[0x1f, 0x17]),
+            Err(ReadPacketError::InvalidPacket)
+        ));
+    }
+
+    #[test]
+    fn packet_try_new() {
+        let data = [0; MAX_DATA_LEN + 1];
+        assert_eq!(
+            Packet::try_new(0x1f, &data[..MAX_DATA_LEN]).unwrap(),
+            Packet::new(0x1f, &data[..MAX_DATA_LEN])
+        );
+        assert!(matches!(
+            Packet::try_new(0x1f, &data),
+            Err(WritePacketError::InvalidLength { len: 23 })
+        ));
+        assert!(Packet::try_from((0x1f, &data[..])).is_err());
+    }
+
+    #[test]
+    fn packet_to_bytes() {
+        let packet = Packet::new(0x1f, &[0, 1, b'h', b'i']);
+        let mut buffer = Vec::new();
+        PacketCodec::new(&mut buffer)
+            .write_packet(&packet)
+            .expect("write failed");
+        assert_eq!(packet.to_bytes(), buffer);
+    }
+
+    proptest! {
+        #[test]
+        fn bytes_roundtrip(
+            packet_type: u8,
+            data in prop::collection::vec(any::<u8>(), 0..=MAX_DATA_LEN),
+        ) {
+            let packet = Packet::new(packet_type, &data);
+            let parsed = Packet::from_bytes(&packet.to_bytes()).expect("parse failed");
+            prop_assert_eq!(parsed, packet);
+        }
+    }
+
+    #[test]
+    fn packet_stream() {
+        let test_packets = [
+            Packet::new(0x40, &[]),
+            Packet::new(0x80, &[5]),
+            Packet::new(0x5f, b"CFA635"),
+        ];
+        let mut buffer = Vec::new();
+        {
+            let mut writer = PacketCodec::new(&mut buffer);
+            for packet in &test_packets {
+                writer.write_packet(packet).expect("write failed");
+            }
+        }
+
+        let read_packets: Vec<Packet> = PacketCodec::new(buffer.as_slice())
+            .packets()
+            .collect::<Result<_, _>>()
+            .expect("read failed");
+        assert_eq!(read_packets, test_packets);
+    }
+
+    #[test]
+    fn packet_stream_truncated() {
+        let mut buffer = Vec::new();
+        PacketCodec::new(&mut buffer)
+            .write_packet(&Packet::new(0x00, b"Hello"))
+            .expect("write failed");
+        buffer.pop();
+
+        let mut packets = PacketCodec::new(buffer.as_slice()).packets();
+        assert!(matches!(packets.next(), Some(Err(ReadPacketError::Io(_)))));
+        assert!(packets.next().is_none());
+    }
+
+    #[test]
+    fn resync_after_corruption() {
+        let good = Packet::new(0x80, &[5]);
+        let mut stream = vec![0x40, 0x03, b'a'];
+        stream.extend(good.to_bytes());
+        let mut corrupted = good.to_bytes();
+        corrupted[2] ^= 0x01;
+        stream.extend(corrupted);
+        stream.extend(good.to_bytes());
+
+        let mut codec = PacketCodec::new(stream.as_slice());
+        assert_eq!(codec.read_packet_resync().unwrap(), (good.clone(), 3));
+        assert_eq!(codec.read_packet_resync().unwrap(), (good, 5));
+        assert_eq!(codec.skipped_len(), 0);
+        assert!(codec.read_packet_resync().is_err());
+    }
+
+    #[test]
+    fn skipped_len_survives_errors() {
+        let mut corrupted = Packet::new(0x80, &[5]).to_bytes();
+        corrupted[2] ^= 0x01;
+
+        let mut codec = PacketCodec::new(corrupted.as_slice());
+        assert!(codec.read_packet_resync().is_err());
+        assert!(codec.skipped_len() > 0);
+        codec.clear_buffer();
+        assert_eq!(codec.skipped_len(), 0);
+    }
+
+    #[test]
+    fn partial_packet_is_kept() {
+        /// Returns its chunks one per read, with a timeout in between.
+        struct Stuttering(VecDeque<Vec<u8>>, bool);
+        impl Read for Stuttering {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.1 = !self.1;
+                if self.1 {
+                    return Err(ErrorKind::TimedOut.into());
+                }
+                let mut chunk = self.0.pop_front().unwrap_or_default();
+                let len = chunk.len().min(buf.len());
+                buf[..len].copy_from_slice(&chunk[..len]);
+                let rest = chunk.split_off(len);
+                if !rest.is_empty() {
+                    self.0.push_front(rest);
+                }
+                Ok(len)
+            }
+        }
+
+        let bytes = Packet::new(0x00, b"Hello").to_bytes();
+        let chunks = bytes.chunks(3).map(<[u8]>::to_vec).collect();
+        let mut codec = PacketCodec::new(Stuttering(chunks, false));
+        let packet = loop {
+            match codec.read_packet() {
+                Ok(packet) => break packet,
+                Err(ReadPacketError::Io(e)) if e.kind() == ErrorKind::TimedOut => continue,
+                Err(e) => panic!("{:?}", e),
+            }
+        };
+        assert_eq!(packet, Packet::new(0x00, b"Hello"));
+        assert_eq!(codec.buffered_len(), 0);
+    }
 }
@@ -0,0 +1,95 @@
+use crate::charset::GlyphBitmap;
+use crate::{CursorStyle, KeyReporting};
+
+/// A complete device setup, applied with
+/// [`Device::apply_config`](crate::Device::apply_config).
+///
+/// Every setting is optional; settings that are `None` are left unchanged.
+///
+/// With the `serde` feature enabled, this can be loaded from a configuration
+/// file. Missing fields take their default values:
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # fn main() -> Result<(), serde_json::Error> {
+/// use cfa635::DeviceConfig;
+///
+/// let config: DeviceConfig = serde_json::from_str(
+///     r#"{
+///         "contrast": 120,
+///         "backlight": 100,
+///         "key_reporting": { "press": ["Enter", "Exit"], "release": [] },
+///         "save_boot_state": true
+///     }"#,
+/// )?;
+/// assert_eq!(config.contrast, Some(120));
+/// assert_eq!(config.cursor_style, None);
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "serde"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct DeviceConfig {
+    /// The LCD contrast (see [`Device::set_contrast`](crate::Device::set_contrast)).
+    pub contrast: Option<u8>,
+
+    /// The screen backlight brightness (see
+    /// [`Device::set_backlight`](crate::Device::set_backlight)).
+    pub backlight: Option<u8>,
+
+    /// The keypad backlight brightness (see
+    /// [`Device::set_backlight`](crate::Device::set_backlight)).
+    pub keypad_backlight: Option<u8>,
+
+    /// The cursor style.
+    pub cursor_style: Option<CursorStyle>,
+
+    /// The cursor position, as `(row, col)`.
+    pub cursor_position: Option<(u8, u8)>,
+
+    /// Which key events are reported.
+    pub key_reporting: Option<KeyReporting>,
+
+    /// Custom characters to define, as `(index, bitmap)` (see
+    /// [`Device::set_custom_character`](crate::Device::set_custom_character)).
+    /// Slots that are not listed are left unchanged.
+    pub custom_chars: Option<Vec<(u8, GlyphBitmap)>>,
+
+    /// Whether to save the resulting state as the boot state.
+    pub save_boot_state: bool,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::Key;
+
+    #[test]
+    fn serde_roundtrip() {
+        let config = DeviceConfig {
+            contrast: Some(120),
+            backlight: Some(100),
+            keypad_backlight: None,
+            cursor_style: Some(CursorStyle::StaticUnderscore),
+            cursor_position: Some((3, 19)),
+            key_reporting: Some(KeyReporting {
+                press: vec![Key::Enter],
+                release: vec![Key::Enter, Key::Exit],
+            }),
+            custom_chars: Some(vec![(0, [0x3f; 8]), (7, [0, 0x0a, 0, 0x11, 0x0e, 0, 0, 0])]),
+            save_boot_state: false,
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<DeviceConfig>(&json).unwrap(), config);
+        assert_eq!(
+            serde_json::from_str::<DeviceConfig>("{}").unwrap(),
+            DeviceConfig::default()
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{check_range, limits, Error};
+
+/// An LCD contrast value, for [`Device::set_contrast`](crate::Device::set_contrast).
+///
+/// Contrast is not a brightness or a percentage: higher values make the
+/// characters darker. From the datasheet:
+///
+/// - 60 = light
+/// - 120 = about right
+/// - 150 = dark
+/// - 151-254 = very dark (may be useful at cold temperatures)
+///
+/// Values below 60 are accepted but are usually too light to read.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::{Contrast, Device};
+///
+/// let mut device = Device::new("/dev/ttyACM0")?;
+/// device.set_contrast(Contrast::normal())?;
+/// device.set_contrast(Contrast::new(135)?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Contrast(u8);
+
+impl Contrast {
+    /// Creates a contrast value.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If `value` is greater than
+    ///   [`limits::MAX_CONTRAST`]. The error's `field` is `"contrast"`.
+    pub fn new(value: u8) -> Result<Self, Error> {
+        check_range("contrast", value, limits::MAX_CONTRAST + 1)?;
+        Ok(Self(value))
+    }
+
+    /// The lightest contrast recommended by the datasheet (60).
+    pub const fn light() -> Self {
+        Self(60)
+    }
+
+    /// The contrast that is "about right" at room temperature (120).
+    pub const fn normal() -> Self {
+        Self(120)
+    }
+
+    /// The darkest contrast of the normal range (150).
+    pub const fn dark() -> Self {
+        Self(150)
+    }
+
+    /// Returns the raw contrast value.
+    pub const fn value(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for Contrast {
+    fn default() -> Self {
+        Self::normal()
+    }
+}
+
+impl From<Contrast> for u8 {
+    fn from(contrast: Contrast) -> Self {
+        contrast.0
+    }
+}
+
+impl From<Contrast> for Option<u8> {
+    fn from(contrast: Contrast) -> Self {
+        Some(contrast.0)
+    }
+}
+
+/// A mapping from ambient temperature to LCD contrast, used by
+/// [`Device::set_contrast_for_temperature`](crate::Device::set_contrast_for_temperature).
+///
+/// The curve is a list of `(celsius, contrast)` points. Between points, the
+/// contrast is interpolated linearly; outside of them, the nearest point's
+/// contrast is used.
+///
+/// LCDs get lighter as they get colder, so colder temperatures need higher
+/// contrast values. The default curve is a starting point based on the
+/// datasheet's guidance (120 is "about right" at room temperature, and values
+/// above 150 may be needed in the cold):
+///
+/// | Temperature | Contrast |
+/// |-------------|----------|
+/// | -20 °C      | 160      |
+/// | 0 °C        | 140      |
+/// | 25 °C       | 120      |
+/// | 50 °C       | 100      |
+/// | 70 °C       | 90       |
+///
+/// Panels vary, so installations with wide temperature ranges should measure
+/// their own curve and pass it to [`ContrastCurve::new`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastCurve {
+    points: Vec<(f32, u8)>,
+}
+
+impl ContrastCurve {
+    /// Creates a curve from `(celsius, contrast)` points, in any order.
+    ///
+    /// # Panics
+    ///
+    /// If `points` is empty, or a temperature is NaN.
+    pub fn new(mut points: Vec<(f32, u8)>) -> Self {
+        assert!(!points.is_empty(), "contrast curve has no points");
+        assert!(
+            points.iter().all(|(celsius, _)| !celsius.is_nan()),
+            "contrast curve has a NaN temperature"
+        );
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { points }
+    }
+
+    /// Returns the contrast for the given temperature.
+    pub fn contrast_at(&self, celsius: f32) -> u8 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if celsius.is_nan() || celsius <= first.0 {
+            return first.1;
+        }
+        if celsius >= last.0 {
+            return last.1;
+        }
+        let upper = self
+            .points
+            .iter()
+            .position(|&(t, _)| t >= celsius)
+            .unwrap_or(self.points.len() - 1);
+        let (t0, c0) = self.points[upper - 1];
+        let (t1, c1) = self.points[upper];
+        let fraction = (celsius - t0) / (t1 - t0);
+        let contrast = f32::from(c0) + fraction * (f32::from(c1) - f32::from(c0));
+        contrast.round() as u8
+    }
+}
+
+impl Default for ContrastCurve {
+    fn default() -> Self {
+        Self::new(vec![
+            (-20.0, 160),
+            (0.0, 140),
+            (25.0, 120),
+            (50.0, 100),
+            (70.0, 90),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contrast_range() {
+        assert_eq!(Contrast::new(254).unwrap().value(), 254);
+        assert!(Contrast::new(255).is_err());
+        assert!(Contrast::light() < Contrast::normal());
+        assert!(Contrast::normal() < Contrast::dark());
+    }
+
+    #[test]
+    fn default_curve() {
+        let curve = ContrastCurve::default();
+        assert_eq!(curve.contrast_at(-40.0), 160);
+        assert_eq!(curve.contrast_at(-20.0), 160);
+        assert_eq!(curve.contrast_at(-10.0), 150);
+        assert_eq!(curve.contrast_at(25.0), 120);
+        assert_eq!(curve.contrast_at(37.5), 110);
+        assert_eq!(curve.contrast_at(60.0), 95);
+        assert_eq!(curve.contrast_at(85.0), 90);
+        assert_eq!(curve.contrast_at(f32::NAN), 160);
+    }
+
+    #[test]
+    fn custom_curve() {
+        let curve = ContrastCurve::new(vec![(30.0, 100), (10.0, 130)]);
+        assert_eq!(curve.contrast_at(0.0), 130);
+        assert_eq!(curve.contrast_at(20.0), 115);
+        assert_eq!(curve.contrast_at(40.0), 100);
+
+        let flat = ContrastCurve::new(vec![(0.0, 120)]);
+        assert_eq!(flat.contrast_at(-50.0), 120);
+        assert_eq!(flat.contrast_at(50.0), 120);
+    }
+}
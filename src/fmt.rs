@@ -0,0 +1,47 @@
+//! Helpers for laying out text on the display.
+
+/// Combines a label and a value into a line of exactly `width` bytes, with
+/// the label on the left and the value right-justified.
+///
+/// ```
+/// assert_eq!(cfa635::fmt::field(b"CPU:", b"45%", 10), b"CPU:   45%");
+/// ```
+///
+/// If both don't fit, the label is truncated so that a space still separates
+/// it from the value. If the value alone is wider than `width`, only its first
+/// `width` bytes are kept and the label is dropped.
+pub fn field(label: &[u8], value: &[u8], width: u8) -> Vec<u8> {
+    let width = usize::from(width);
+    if value.len() >= width {
+        return value[..width].to_vec();
+    }
+    let label_space = (width - value.len()).saturating_sub(1);
+    let label = &label[..label.len().min(label_space)];
+
+    let mut line = Vec::with_capacity(width);
+    line.extend_from_slice(label);
+    line.resize(width - value.len(), b' ');
+    line.extend_from_slice(value);
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_alignment() {
+        assert_eq!(field(b"RAM:", b"3.2G", 20), b"RAM:            3.2G");
+        assert_eq!(field(b"", b"42", 5), b"   42");
+        assert_eq!(field(b"Label", b"", 8), b"Label   ");
+        assert_eq!(field(b"", b"", 3), b"   ");
+    }
+
+    #[test]
+    fn field_overflow() {
+        assert_eq!(field(b"Temperature", b"45C", 10), b"Temper 45C");
+        assert_eq!(field(b"Temp", b"123", 4), b" 123");
+        assert_eq!(field(b"Temp", b"1234", 4), b"1234");
+        assert_eq!(field(b"Temp", b"12345", 4), b"1234");
+    }
+}
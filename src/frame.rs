@@ -0,0 +1,95 @@
+use crate::{
+    check_len, check_range, CursorStyle, Error, IntoLed, LedColor, NUM_COLUMNS, NUM_LEDS, NUM_ROWS,
+};
+
+/// A description of the desired state of the display, applied with
+/// [`Device::apply_frame`](crate::Device::apply_frame).
+///
+/// Every part of the frame is optional. Parts that are not specified are left
+/// as they are on the device.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::{Device, Frame, LedColor};
+///
+/// let mut device = Device::new("/dev/ttyACM0")?;
+/// let frame = Frame::new()
+///     .row(0, b"Status: OK")?
+///     .led(0, LedColor::new(0, 100))?
+///     .backlight(100, 50);
+/// device.apply_frame(&frame)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame {
+    pub(crate) rows: [Option<[u8; NUM_COLUMNS as usize]>; NUM_ROWS as usize],
+    pub(crate) cursor_position: Option<(u8, u8)>,
+    pub(crate) cursor_style: Option<CursorStyle>,
+    pub(crate) leds: [Option<LedColor>; NUM_LEDS as usize],
+    pub(crate) backlight: Option<(u8, u8)>,
+}
+
+impl Frame {
+    /// Creates an empty frame, which leaves everything unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the text of a row.
+    ///
+    /// The text is padded with spaces to the full width of the row.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the row index is out of bounds (as defined by
+    ///   [`NUM_ROWS`]), or if the text is longer than [`NUM_COLUMNS`]. The
+    ///   error's `field` is `"row"` or `"text"` respectively.
+    pub fn row(mut self, row: u8, text: &[u8]) -> Result<Self, Error> {
+        check_range("row", row, NUM_ROWS)?;
+        check_len("text", text, NUM_COLUMNS.into())?;
+        let mut line = [b' '; NUM_COLUMNS as usize];
+        line[..text.len()].copy_from_slice(text);
+        self.rows[usize::from(row)] = Some(line);
+        Ok(self)
+    }
+
+    /// Sets the cursor position.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the row or column index is out of bounds (as
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]). The error's `field` is
+    ///   `"row"` or `"col"` respectively.
+    pub fn cursor_position(mut self, row: u8, col: u8) -> Result<Self, Error> {
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        self.cursor_position = Some((row, col));
+        Ok(self)
+    }
+
+    /// Sets the cursor style.
+    pub fn cursor_style(mut self, style: CursorStyle) -> Self {
+        self.cursor_style = Some(style);
+        self
+    }
+
+    /// Sets the color of an indicator LED.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the LED is given as a `u8` index that is out
+    ///   of bounds (as defined by [`NUM_LEDS`]). The error's `field` is
+    ///   `"index"`.
+    pub fn led(mut self, index: impl IntoLed, color: LedColor) -> Result<Self, Error> {
+        let index = index.into_led()?.index();
+        self.leds[usize::from(index)] = Some(color);
+        Ok(self)
+    }
+
+    /// Sets the backlight brightness of the screen and keypad.
+    pub fn backlight(mut self, screen: u8, keypad: u8) -> Self {
+        self.backlight = Some((screen, keypad));
+        self
+    }
+}
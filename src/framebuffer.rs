@@ -0,0 +1,95 @@
+use crate::{check_len, check_range, encode_str, Error, Screen, NUM_COLUMNS, NUM_ROWS};
+
+/// A host-side copy of the screen contents, sent to the display with
+/// [`Device::flush_framebuffer`](crate::Device::flush_framebuffer).
+///
+/// Writing to a framebuffer does not communicate with the device. When it is
+/// flushed, only the characters that differ from what the display shows are
+/// sent, which avoids the flicker of clearing and redrawing the whole screen.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::{Device, Framebuffer};
+///
+/// let mut device = Device::new("/dev/ttyACM0")?;
+/// let mut fb = Framebuffer::new();
+/// for count in 0..100 {
+///     fb.clear();
+///     fb.write_str(0, 0, "Count:")?;
+///     fb.write_str(0, 7, &count.to_string())?;
+///     // Only the digits are sent after the first flush.
+///     device.flush_framebuffer(&fb)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Framebuffer {
+    screen: Screen,
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer filled with spaces.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fills the framebuffer with spaces.
+    pub fn clear(&mut self) {
+        self.screen = Screen::blank();
+    }
+
+    /// Writes raw character codes, starting at the given position.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the row or column index is out of bounds (as
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]), or if the text does
+    ///   not fit between `col` and the right edge of the row. The error's
+    ///   `field` is `"row"`, `"col"` or `"text"` respectively.
+    pub fn write(&mut self, row: u8, col: u8, text: &[u8]) -> Result<(), Error> {
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        check_len("text", text, usize::from(NUM_COLUMNS - col))?;
+        let start = usize::from(col);
+        self.screen.rows[usize::from(row)][start..start + text.len()].copy_from_slice(text);
+        Ok(())
+    }
+
+    /// Like [`Framebuffer::write`], but takes a string and converts it to the
+    /// display's character set, as [`Device::set_str`](crate::Device::set_str)
+    /// does.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Device::set_str`](crate::Device::set_str).
+    pub fn write_str(&mut self, row: u8, col: u8, text: &str) -> Result<(), Error> {
+        self.write(row, col, &encode_str(text)?)
+    }
+
+    /// Returns the contents of the framebuffer.
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_clear() {
+        let mut fb = Framebuffer::new();
+        fb.write(1, 18, b"ab").unwrap();
+        fb.write_str(1, 0, "Hi").unwrap();
+        assert_eq!(fb.screen().row(1).unwrap(), b"Hi                ab");
+        assert!(fb.write(1, 19, b"ab").is_err());
+        assert!(matches!(
+            fb.write_str(0, 0, "é"),
+            Err(Error::UnmappableCharacter(_))
+        ));
+
+        fb.clear();
+        assert_eq!(fb.screen(), &Screen::blank());
+    }
+}
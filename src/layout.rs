@@ -0,0 +1,87 @@
+//! Validation of screen layouts before they are sent to the device.
+
+use crate::{NUM_COLUMNS, NUM_ROWS};
+use thiserror::Error;
+
+/// A problem with one of the placements passed to [`validate`].
+///
+/// `index` is the position of the offending placement in the slice.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum LayoutError {
+    /// The row is not in `0..NUM_ROWS`.
+    #[error("placement {index}: row {row} is out of bounds")]
+    RowOutOfBounds { index: usize, row: u8 },
+
+    /// The column is not in `0..NUM_COLUMNS`.
+    #[error("placement {index}: column {col} is out of bounds")]
+    ColumnOutOfBounds { index: usize, col: u8 },
+
+    /// The text does not fit between its column and the right edge of the
+    /// screen, and would wrap onto the next row.
+    #[error("placement {index}: {len} bytes at column {col} would wrap past the right edge")]
+    Wraps { index: usize, col: u8, len: usize },
+}
+
+/// Checks that a set of text placements fits on the screen.
+///
+/// Each placement is a `(row, col, text)` tuple, as would be passed to
+/// [`Device::set_text`](crate::Device::set_text). A placement is valid if its
+/// position is on the screen and its text ends at or before the right edge of
+/// its row, which is also what `Device::set_text` requires.
+///
+/// Returns the first invalid placement as an error.
+pub fn validate(placements: &[(u8, u8, &[u8])]) -> Result<(), LayoutError> {
+    for (index, &(row, col, text)) in placements.iter().enumerate() {
+        if row >= NUM_ROWS {
+            return Err(LayoutError::RowOutOfBounds { index, row });
+        }
+        if col >= NUM_COLUMNS {
+            return Err(LayoutError::ColumnOutOfBounds { index, col });
+        }
+        if usize::from(col) + text.len() > NUM_COLUMNS.into() {
+            return Err(LayoutError::Wraps {
+                index,
+                col,
+                len: text.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_layout() {
+        let placements: [(u8, u8, &[u8]); 4] = [
+            (0, 0, b"CPU:"),
+            (0, 16, b"45%"),
+            (3, 0, b"12345678901234567890"),
+            (3, 19, b""),
+        ];
+        assert_eq!(validate(&placements), Ok(()));
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        assert_eq!(
+            validate(&[(0, 0, b"ok"), (4, 0, b"x")]),
+            Err(LayoutError::RowOutOfBounds { index: 1, row: 4 })
+        );
+        assert_eq!(
+            validate(&[(1, 20, b"x")]),
+            Err(LayoutError::ColumnOutOfBounds { index: 0, col: 20 })
+        );
+        assert_eq!(
+            validate(&[(2, 15, b"123456"), (9, 9, b"")]),
+            Err(LayoutError::Wraps {
+                index: 0,
+                col: 15,
+                len: 6
+            })
+        );
+    }
+}
@@ -1,9 +1,51 @@
-pub(crate) mod codec;
+#[cfg(feature = "tokio")]
+mod async_device;
+mod batch;
+mod boot;
+mod builder;
+pub mod charset;
+mod clock;
+pub mod codec;
+mod config;
+mod contrast;
+pub mod fmt;
+mod frame;
+mod framebuffer;
+pub mod layout;
+pub mod limits;
+mod marquee;
+#[cfg(test)]
+mod mock;
+mod model;
+pub mod quick;
+mod screen;
+mod transport;
+pub mod widgets;
 
+#[cfg(feature = "tokio")]
+pub use self::async_device::AsyncDevice;
+pub use self::batch::Batch;
+pub use self::boot::{BootState, BootStateMismatch};
+pub use self::builder::DeviceBuilder;
+pub use self::config::DeviceConfig;
+pub use self::contrast::{Contrast, ContrastCurve};
+pub use self::frame::Frame;
+pub use self::framebuffer::Framebuffer;
+pub use self::marquee::{Marquee, DEFAULT_MARQUEE_GAP};
+pub use self::model::Model;
+pub use self::screen::Screen;
+pub use self::transport::Transport;
+
+use self::clock::{Clock, SystemClock};
 use self::codec::{Packet, PacketCodec, ReadPacketError, WritePacketError, MAX_DATA_LEN};
-use serialport::SerialPort;
+use self::transport::NullTransport;
+use serialport::{SerialPort, SerialPortType, UsbPortInfo};
 use std::collections::VecDeque;
-use std::time::Duration;
+use std::io::{BufRead, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// How many rows (lines) the display has.
@@ -27,9 +69,79 @@ pub const NUM_COLUMNS: u8 = 20;
 /// bound).
 pub const NUM_LEDS: u8 = 4;
 
+/// How many GPIO pins the device has, including those that drive the
+/// indicator LEDs (see [`Device::led_gpio_pins`]).
+///
+/// Acceptable pin indices are in the range `0..NUM_GPIO_PINS` (note the
+/// exclusive upper bound).
+pub const NUM_GPIO_PINS: u8 = 13;
+
+/// The baud rate used by [`Device::new`], which is also the device's factory
+/// setting.
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// The serial port read timeout used by [`Device::new`] (see
+/// [`DeviceBuilder::timeout`]).
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// The longest serial port read timeout accepted by
+/// [`DeviceBuilder::timeout`].
+pub const MAX_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The default for [`Device::set_command_timeout`].
+const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The default delay before the first retry (see [`Device::set_retries`]).
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// The bits of a custom character bitmap row that are pixels.
+const GLYPH_PIXEL_MASK: u8 = (1 << limits::CUSTOM_CHAR_WIDTH) - 1;
+
+/// How long to sleep between checks for incoming reports when waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 pub struct Device {
-    codec: PacketCodec<Box<dyn SerialPort>>,
-    report_buffer: VecDeque<Report>,
+    codec: PacketCodec<Box<dyn Transport>>,
+    report_buffer: VecDeque<(Instant, Report)>,
+    /// Mask of keys whose last returned report was a press.
+    held_keys: u8,
+    key_reporting: Option<KeyReporting>,
+    dry_run: Option<Vec<Packet>>,
+    led_colors: [Option<LedColor>; NUM_LEDS as usize],
+    /// The last visible cursor style set on this connection.
+    cursor_style: Option<CursorStyle>,
+    cache: StateCache,
+    last_frame: Frame,
+    /// Packets sent in the current batch whose responses have not been read.
+    batch: Option<Vec<Packet>>,
+    /// The screen contents as written through this device.
+    shadow: Screen,
+    /// Whether `shadow` covers the whole display, i.e. the screen has been
+    /// cleared, read back or fully written since connecting or invalidating.
+    shadow_known: bool,
+    interrupted: Arc<AtomicBool>,
+    command_timeout: Option<Duration>,
+    retries: u8,
+    retry_backoff: Duration,
+    discarded_packets: u64,
+    /// The maximum number of buffered reports, if limited.
+    report_limit: Option<usize>,
+    report_overflow: ReportOverflow,
+    dropped_reports: u64,
+    contrast_curve: ContrastCurve,
+    clock: Box<dyn Clock>,
+    model: Model,
+}
+
+/// Host-side record of device settings that were last sent or read back.
+///
+/// `None` means the value is unknown.
+#[derive(Debug, Clone, Default)]
+struct StateCache {
+    contrast: Option<u8>,
+    screen_backlight: Option<u8>,
+    keypad_backlight: Option<u8>,
+    cursor_position: Option<(u8, u8)>,
 }
 
 impl Device {
@@ -39,15 +151,282 @@ impl Device {
     ///
     /// On Linux, the name is typically the path to the device (e.g.
     /// `/dev/ttyACM0` or `/dev/serial/by-id/...`)
+    ///
+    /// Any input already waiting on the port when it is opened is discarded
+    /// (see [`Device::flush_input`]).
+    ///
+    /// Note: If key reporting was saved in the boot state, the device starts
+    /// sending reports as soon as keys are pressed, even before the first
+    /// command. Reports that arrive after connecting are buffered by the
+    /// first command and returned by [`Device::poll_report`] like any other.
+    /// Call [`Device::discard_reports`] when the application is ready to
+    /// start from a clean slate.
     pub fn new<P: AsRef<str>>(path: P) -> Result<Self, Error> {
-        //TODO baud rate API - not relevant for USB version
-        let port = serialport::new(path.as_ref(), 115200)
-            .timeout(Duration::from_millis(250))
-            .open()?;
-        Ok(Self {
-            codec: PacketCodec::new(port),
+        Self::with_baud(path, DEFAULT_BAUD_RATE)
+    }
+
+    /// Connect to a device using the named serial port, at the given baud
+    /// rate.
+    ///
+    /// The baud rate only matters for the RS232 versions of the display,
+    /// where it is configurable (19200 or 115200). The USB version ignores
+    /// it. [`Device::new`] uses [`DEFAULT_BAUD_RATE`].
+    ///
+    /// This is a shorthand for [`Device::builder`] with
+    /// [`DeviceBuilder::baud`].
+    pub fn with_baud<P: AsRef<str>>(path: P, baud_rate: u32) -> Result<Self, Error> {
+        Self::builder(path.as_ref()).baud(baud_rate).open()
+    }
+
+    /// Returns a builder for connecting to a device on the named serial port
+    /// with non-default options.
+    ///
+    /// Nothing is opened until [`DeviceBuilder::open`] is called.
+    pub fn builder<P: Into<String>>(path: P) -> DeviceBuilder {
+        DeviceBuilder::new(path.into())
+    }
+
+    /// Wraps a serial port that has already been opened and configured.
+    ///
+    /// The port should have a read timeout set (e.g.
+    /// [`DEFAULT_READ_TIMEOUT`], which is what [`Device::new`] uses). Without
+    /// one, a command that never receives a
+    /// response will block forever.
+    pub fn from_serial_port(port: Box<dyn SerialPort>) -> Self {
+        Self::with_transport(Box::new(port))
+    }
+
+    /// Connects to a device through a custom [`Transport`], such as an
+    /// in-memory fake for testing.
+    pub fn from_transport<T: Transport + 'static>(transport: T) -> Self {
+        Self::with_transport(Box::new(transport))
+    }
+
+    /// Connect to a device using the named serial port, retrying if the port
+    /// cannot be opened.
+    ///
+    /// This is useful when the device may not be available yet, e.g. in a
+    /// service that starts before the USB device has been enumerated.
+    ///
+    /// Up to `attempts` attempts are made (at least one). After each failed
+    /// attempt, this sleeps before trying again, starting with `backoff` and
+    /// doubling the delay every time. If all attempts fail, the last error is
+    /// returned.
+    pub fn open_with_retry<P: AsRef<str>>(
+        path: P,
+        attempts: u32,
+        backoff: Duration,
+    ) -> Result<Self, Error> {
+        Self::builder(path.as_ref()).open_with_retry(attempts, backoff)
+    }
+
+    /// Creates a device that is not connected to anything, and instead
+    /// captures the packets that would have been sent.
+    ///
+    /// Every command succeeds with a synthetic response: pings echo their
+    /// payload, and all other commands receive an empty response. Commands
+    /// that decode data from their response (e.g. [`Device::read_status`])
+    /// will therefore fail with `InvalidRead`. Reports cannot be simulated
+    /// in this mode, so [`Device::poll_report`] always returns `None`.
+    ///
+    /// The captured packets can be retrieved with
+    /// [`Device::captured_packets`].
+    pub fn dry_run() -> Self {
+        let mut device = Self::with_transport(Box::new(NullTransport));
+        device.dry_run = Some(Vec::new());
+        device
+    }
+
+    /// Returns the packets captured by a [dry-run](Device::dry_run) device,
+    /// in the order they were sent.
+    ///
+    /// Always empty for devices that are connected to a real port.
+    pub fn captured_packets(&self) -> &[Packet] {
+        self.dry_run.as_deref().unwrap_or_default()
+    }
+
+    fn with_transport(transport: Box<dyn Transport>) -> Self {
+        Self {
+            codec: PacketCodec::new(transport),
             report_buffer: VecDeque::new(),
-        })
+            held_keys: 0,
+            key_reporting: None,
+            dry_run: None,
+            led_colors: [None; NUM_LEDS as usize],
+            cursor_style: None,
+            cache: StateCache::default(),
+            last_frame: Frame::new(),
+            batch: None,
+            shadow: Screen::blank(),
+            shadow_known: false,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            command_timeout: Some(DEFAULT_COMMAND_TIMEOUT),
+            retries: 0,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            discarded_packets: 0,
+            report_limit: None,
+            report_overflow: ReportOverflow::default(),
+            dropped_reports: 0,
+            contrast_curve: ContrastCurve::default(),
+            clock: Box::new(SystemClock),
+            model: Model::default(),
+        }
+    }
+
+    /// Returns the name of the serial port the device is connected to, as it
+    /// was passed to [`Device::new`] (e.g. `/dev/ttyACM0` or `COM3`).
+    ///
+    /// Returns `None` if the port does not report a name, or if the device is
+    /// not connected to a serial port (e.g. [`Device::dry_run`]).
+    pub fn port_name(&self) -> Option<String> {
+        self.codec.inner().port_name()
+    }
+
+    /// Returns the baud rate of the serial port the device is connected to,
+    /// as reported by the port.
+    ///
+    /// Returns `None` if it cannot be determined, or if the device is not
+    /// connected to a serial port (e.g. [`Device::dry_run`]).
+    pub fn baud_rate(&self) -> Option<u32> {
+        self.codec.inner().baud_rate()
+    }
+
+    /// Looks up the USB descriptor of the port the device is connected to.
+    ///
+    /// Returns `None` if the port has no name (see [`Device::port_name`]), or
+    /// if it is not found among the system's USB serial ports.
+    ///
+    /// Note: The vendor and product IDs are available on all supported
+    /// platforms, but the string fields (serial number, manufacturer and
+    /// product) are only filled in if the operating system provides them. On
+    /// Linux, enumerating ports requires the `serialport` crate's `libudev`
+    /// feature (enabled by default).
+    pub fn usb_info(&self) -> Result<Option<UsbPortInfo>, Error> {
+        let name = match self.port_name() {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        // Resolve symlinks like `/dev/serial/by-id/...` to the device node.
+        let canonical = |name: &str| std::fs::canonicalize(name).ok();
+        let target = canonical(&name);
+        let info = serialport::available_ports()?
+            .into_iter()
+            .find(|port| {
+                port.port_name == name || (target.is_some() && canonical(&port.port_name) == target)
+            })
+            .and_then(|port| match port.port_type {
+                SerialPortType::UsbPort(info) => Some(info),
+                _ => None,
+            });
+        Ok(info)
+    }
+
+    /// Returns a handle that can be used to interrupt blocking calls on this
+    /// device from another thread.
+    ///
+    /// See [`InterruptHandle`] for details.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            interrupted: self.interrupted.clone(),
+        }
+    }
+
+    /// Sets the maximum total time a command may take, or `None` for no limit.
+    ///
+    /// This is separate from the serial port's read timeout, which applies to
+    /// each individual read. While waiting for a response, the device may
+    /// keep receiving reports, so a command could otherwise wait indefinitely
+    /// even though no single read times out. When the limit is exceeded, the
+    /// command fails with `Timeout`.
+    ///
+    /// The default is 1 second.
+    pub fn set_command_timeout(&mut self, timeout: impl Into<Option<Duration>>) {
+        self.command_timeout = timeout.into();
+    }
+
+    /// Sets how many times a command is retried if its response is invalid,
+    /// and how long to wait before the first retry.
+    ///
+    /// A command whose response fails with `InvalidRead` is sent again, after
+    /// discarding any pending input (see [`Device::flush_input`]). This
+    /// includes a response with a corrupted byte on the line: it fails its CRC
+    /// check and is skipped, and if no valid response follows before the read
+    /// times out, the command fails with `InvalidRead` rather than `Io`. The
+    /// wait doubles after each retry. Other errors are not retried. Reports
+    /// that were waiting in the input buffer are lost when it is flushed.
+    ///
+    /// This assumes that commands are safe to repeat, which holds for the
+    /// commands wrapped by this crate. Keep it in mind when retrying raw
+    /// commands (see [`Device::raw_transact`]).
+    ///
+    /// The default is 0 (no retries).
+    pub fn set_retries(&mut self, retries: u8, backoff: Duration) {
+        self.retries = retries;
+        self.retry_backoff = backoff;
+    }
+
+    /// Limits how many reports are buffered, or `None` for no limit.
+    ///
+    /// Reports that arrive while waiting for a command's response are
+    /// buffered until they are polled. If they are never polled, the buffer
+    /// grows without bound; with a limit, reports are dropped instead,
+    /// according to `overflow`. Dropped reports are counted by
+    /// [`Device::dropped_reports_count`].
+    ///
+    /// If the buffer already holds more reports than the new limit, the
+    /// excess is dropped right away. The default is no limit.
+    pub fn set_report_buffer_limit(
+        &mut self,
+        limit: impl Into<Option<usize>>,
+        overflow: ReportOverflow,
+    ) {
+        self.report_limit = limit.into();
+        self.report_overflow = overflow;
+        if let Some(limit) = self.report_limit {
+            while self.report_buffer.len() > limit {
+                match overflow {
+                    ReportOverflow::DropOldest => self.report_buffer.pop_front(),
+                    ReportOverflow::DropNewest => self.report_buffer.pop_back(),
+                };
+                self.dropped_reports += 1;
+            }
+        }
+    }
+
+    /// Returns how many reports were dropped because the report buffer was
+    /// full (see [`Device::set_report_buffer_limit`]).
+    pub fn dropped_reports_count(&self) -> u64 {
+        self.dropped_reports
+    }
+
+    /// Adds a report that arrived while waiting for something else to the
+    /// report buffer, respecting the buffer limit.
+    fn buffer_report(&mut self, report: Report) {
+        if self.report_limit == Some(0) {
+            self.dropped_reports += 1;
+            return;
+        }
+        if self.report_limit == Some(self.report_buffer.len()) {
+            log::warn!("report buffer is full, dropping a report");
+            self.dropped_reports += 1;
+            match self.report_overflow {
+                ReportOverflow::DropOldest => {
+                    self.report_buffer.pop_front();
+                }
+                ReportOverflow::DropNewest => return,
+            }
+        }
+        self.report_buffer.push_back((self.clock.now(), report));
+    }
+
+    /// Returns `Interrupted` if an interrupt was requested, and clears the
+    /// request.
+    fn check_interrupted(&self) -> Result<(), Error> {
+        if self.interrupted.swap(false, Ordering::SeqCst) {
+            Err(Error::Interrupted)
+        } else {
+            Ok(())
+        }
     }
 
     fn send(&mut self, packet: &Packet) -> Result<(), Error> {
@@ -56,55 +435,412 @@ impl Device {
         Ok(())
     }
 
+    /// Reads the next valid packet.
+    ///
+    /// If bytes had to be skipped to resynchronize with the device and the
+    /// read then fails (e.g. a corrupted response followed by a timeout),
+    /// `InvalidRead` is returned instead of the read error, so that the
+    /// command can be retried.
     fn recv(&mut self) -> Result<Packet, Error> {
-        let packet = self.codec.read_packet()?;
-        log::trace!("received {:?}", packet);
-        if packet.check_crc() {
-            Ok(packet)
-        } else {
-            //TODO ignore+warn?
-            Err(Error::InvalidRead)
+        let (packet, skipped) = match self.codec.read_packet_resync() {
+            Ok(result) => result,
+            Err(e) if self.codec.skipped_len() > 0 => {
+                log::warn!(
+                    "skipped {} bytes without finding a valid packet: {}",
+                    self.codec.skipped_len(),
+                    e
+                );
+                return Err(Error::InvalidRead);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if skipped > 0 {
+            log::warn!("skipped {} bytes to resynchronize with the device", skipped);
         }
+        log::trace!("received {:?}", packet);
+        Ok(packet)
     }
 
     fn transact(&mut self, packet: &Packet) -> Result<Packet, Error> {
+        if let Some(captured) = &mut self.dry_run {
+            log::trace!("capturing {:?}", packet);
+            captured.push(packet.clone());
+            let data = if packet.packet_type() == 0x00 {
+                packet.data()
+            } else {
+                &[]
+            };
+            return Ok(Packet::new(0x40 | packet.packet_type(), data));
+        }
         self.send(packet)?;
+        if let Some(batch) = &mut self.batch {
+            // The response is read when the batch is finished.
+            batch.push(packet.clone());
+            return Ok(Packet::new(0x40 | packet.packet_type(), &[]));
+        }
+        let mut retries = self.retries;
+        let mut backoff = self.retry_backoff;
         loop {
+            match self.await_response(packet) {
+                Err(Error::InvalidRead) if retries > 0 => {
+                    log::debug!(
+                        "invalid response to {:?}, retrying in {:?}",
+                        packet,
+                        backoff
+                    );
+                    retries -= 1;
+                    self.clock.sleep(backoff);
+                    backoff *= 2;
+                    self.flush_input()?;
+                    self.send(packet)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Reads packets until the response to `packet` arrives, buffering any
+    /// reports that arrive before it.
+    fn await_response(&mut self, packet: &Packet) -> Result<Packet, Error> {
+        let deadline = self
+            .command_timeout
+            .map(|timeout| self.clock.now() + timeout);
+        loop {
+            self.check_interrupted()?;
+            if deadline.is_some_and(|deadline| self.clock.now() >= deadline) {
+                log::warn!("timed out waiting for a response to {:?}", packet);
+                return Err(Error::Timeout);
+            }
             let response = self.recv()?;
             let resp_class = response.packet_type() >> 6;
-            let resp_code = response.packet_type() & 0x3f;
+            let resp_code = response.packet_type() & limits::MAX_COMMAND;
             if resp_class == 0b10 {
                 if let Some(report) = Report::from_raw(&response) {
-                    self.report_buffer.push_back(report);
+                    self.buffer_report(report);
                 }
             } else if resp_class == 0b01 && resp_code == packet.packet_type() {
                 // normal response code
-                return Ok(response);
+                match expected_response_len(packet) {
+                    Some(len) if len != response.data().len() => {
+                        log::warn!("expected {} bytes in response, got {:?}", len, response);
+                        return Err(Error::InvalidRead);
+                    }
+                    _ => return Ok(response),
+                }
             } else if resp_class == 0b11 && resp_code == packet.packet_type() {
                 // error response code
-                return Err(Error::ReturnedError);
+                return Err(Error::ReturnedError {
+                    command: packet.packet_type(),
+                    data: response.data().to_vec(),
+                });
             } else {
                 log::warn!("unexpected packet received: {:?}", response);
             }
         }
     }
 
+    /// Sends several commands back-to-back, and then waits for all of their
+    /// responses.
+    ///
+    /// The commands queued through the [`Batch`] passed to `f` are written as
+    /// soon as they are queued, without waiting for each response in turn.
+    /// This saves a round trip per command, e.g. when refreshing the whole
+    /// screen. Only commands that do not return data can be batched.
+    ///
+    /// The device processes commands in the order they are sent and responds
+    /// to each one in that order, so the responses are matched to the
+    /// commands one by one once `f` returns. Reports that arrive in between
+    /// are buffered as usual.
+    ///
+    /// # Errors
+    ///
+    /// If `f` returns an error (e.g. `InvalidArgument` from one of the queued
+    /// commands), the commands queued before it are still completed, and the
+    /// error is returned.
+    ///
+    /// If the device rejects a command, the remaining responses are still
+    /// read, and `ReturnedError` is returned for the first rejected command.
+    /// As the commands were sent before knowing whether they succeeded,
+    /// host-side state like [`Device::cursor_position`] is then forgotten, as
+    /// with [`Device::invalidate_cache`].
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), cfa635::Error> {
+    /// use cfa635::{Device, LedColor};
+    ///
+    /// let mut device = Device::new("/dev/ttyACM0")?;
+    /// device.batch(|batch| {
+    ///     batch.clear_screen()?;
+    ///     batch.set_text(0, 0, b"Status: OK")?;
+    ///     batch.set_led_color(0, LedColor::GREEN)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn batch<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Batch) -> Result<(), Error>,
+    {
+        self.batch = Some(Vec::new());
+        let result = f(&mut Batch { device: self });
+        let sent = self.batch.take().unwrap_or_default();
+        let mut rejected = None;
+        for packet in &sent {
+            match self.await_response(packet) {
+                Ok(_) => {}
+                Err(err @ Error::ReturnedError { .. }) => {
+                    rejected.get_or_insert(err);
+                }
+                // The remaining responses cannot be matched up reliably.
+                Err(err) => {
+                    self.invalidate_cache();
+                    return Err(err);
+                }
+            }
+        }
+        if let Some(err) = rejected {
+            self.invalidate_cache();
+            return Err(err);
+        }
+        result
+    }
+
+    /// Sends a command without waiting for its response.
+    ///
+    /// This avoids a round trip per command, which can speed up large batches
+    /// of updates at the cost of not knowing whether each one succeeded.
+    ///
+    /// Note: The device still sends a response for every command. These
+    /// responses stay in the input buffer until they are read, and are
+    /// discarded by [`Device::poll_report`] as it looks for reports. Make sure
+    /// they are drained eventually (for example by polling until it returns
+    /// `None`). A large backlog can overflow the port's input buffer and cause
+    /// later commands to fail.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If `command` is greater than
+    ///   [`limits::MAX_COMMAND`] (field `"command"`), or `data` is longer than
+    ///   [`MAX_DATA_LEN`] (field `"data"`).
+    pub fn send_no_wait(&mut self, command: u8, data: &[u8]) -> Result<(), Error> {
+        check_range("command", command, limits::MAX_COMMAND + 1)?;
+        check_len("data", data, MAX_DATA_LEN)?;
+        let packet = Packet::new(command, data);
+        if let Some(captured) = &mut self.dry_run {
+            log::trace!("capturing {:?}", packet);
+            captured.push(packet);
+            return Ok(());
+        }
+        self.send(&packet)
+    }
+
+    /// Sends an arbitrary command and returns the data of its response.
+    ///
+    /// This is an escape hatch for commands that this crate does not wrap.
+    /// The command is framed and checked like any other: reports that arrive
+    /// before the response are buffered for [`Device::poll_report`], and the
+    /// command timeout applies.
+    ///
+    /// Host-side state like [`Device::cursor_position`] is not updated for
+    /// raw commands. If the command changes something this crate keeps track
+    /// of, call [`Device::invalidate_cache`] afterwards.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), cfa635::Error> {
+    /// use cfa635::Device;
+    ///
+    /// let mut device = Device::new("/dev/ttyACM0")?;
+    /// // Command 1: Get Hardware & Firmware Version
+    /// let version = device.raw_transact(0x01, &[])?;
+    /// println!("{}", String::from_utf8_lossy(&version));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If `command` is greater than
+    ///   [`limits::MAX_COMMAND`] (field `"command"`), or `data` is longer than
+    ///   [`MAX_DATA_LEN`] (field `"data"`).
+    ///
+    /// - `ReturnedError` - If the device responded with an error.
+    pub fn raw_transact(&mut self, command: u8, data: &[u8]) -> Result<Vec<u8>, Error> {
+        check_range("command", command, limits::MAX_COMMAND + 1)?;
+        let response = self.transact(&Packet::try_new(command, data)?)?;
+        Ok(response.data().to_vec())
+    }
+
     /// Sends a "Ping" with an arbitrary payload.
     ///
     /// If a correct response is received, this call will return `Ok` with the
     /// bytes sent back from the device. The data returned should be equal to
     /// the payload that was sent.
     ///
-    /// Note: The maximum payload size is 16 bytes. If the provided data is
-    /// longer, only the first 16 bytes will be sent (and therefore, only up to
-    /// 16 bytes will be received).
+    /// Note: The maximum payload size is 16 bytes
+    /// ([`limits::MAX_PING_LEN`]). If the provided data is longer, only the
+    /// first 16 bytes will be sent (and therefore, only up to 16 bytes will be
+    /// received).
     pub fn ping(&mut self, data: &[u8]) -> Result<Vec<u8>, Error> {
-        // Max data is 16 bytes.
-        let payload = &data[..data.len().min(16)];
+        let payload = &data[..data.len().min(limits::MAX_PING_LEN)];
         let pong = self.transact(&Packet::new(0x00, payload))?;
         Ok(pong.data().to_owned())
     }
 
+    /// Checks that the device is still connected and responding, by sending
+    /// a ping and checking that the payload is echoed back.
+    ///
+    /// Any error is reported as `false` (and logged at debug level), which
+    /// makes this convenient for health checks and reconnection loops. Use
+    /// [`Device::ping`] to find out what went wrong.
+    pub fn is_alive(&mut self) -> bool {
+        const PAYLOAD: &[u8] = b"cfa635 alive?";
+        match self.ping(PAYLOAD) {
+            Ok(echo) if echo == PAYLOAD => true,
+            Ok(echo) => {
+                log::debug!("ping echoed {:?} instead of {:?}", echo, PAYLOAD);
+                false
+            }
+            Err(err) => {
+                log::debug!("ping failed: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Checks that the device uses the same packet CRC algorithm as this crate.
+    ///
+    /// This sends a few pings and inspects the responses. If every response
+    /// echoes the payload correctly but fails the CRC check, the firmware
+    /// calculates CRCs differently, and `CrcAlgorithmMismatch` is returned.
+    /// Without this check, every response from such a device is treated as
+    /// corrupted and skipped, so every command fails with a less helpful
+    /// timeout.
+    ///
+    /// No firmware versions are currently known to be affected. This is a
+    /// diagnostic for connecting to unknown or modified firmware.
+    ///
+    /// # Errors
+    ///
+    /// - `CrcAlgorithmMismatch` - As described above.
+    ///
+    /// - `InvalidRead` - If a response fails the CRC check and is also
+    ///   corrupted, which suggests a noisy connection rather than a different
+    ///   algorithm.
+    pub fn check_crc_compatibility(&mut self) -> Result<(), Error> {
+        const PROBE: &[u8] = b"crc probe";
+        const ATTEMPTS: usize = 3;
+
+        if self.dry_run.is_some() {
+            return Ok(());
+        }
+        for _ in 0..ATTEMPTS {
+            self.send(&Packet::new(0x00, PROBE))?;
+            let response = loop {
+                self.check_interrupted()?;
+                // Read without `recv`, which rejects bad CRCs.
+                let packet = self.codec.read_packet()?;
+                log::trace!("received {:?}", packet);
+                if packet.packet_type() == 0x40 {
+                    break packet;
+                } else if let Some(report) = Report::from_raw(&packet) {
+                    if packet.check_crc() {
+                        self.buffer_report(report);
+                    }
+                }
+            };
+            if response.check_crc() {
+                return Ok(());
+            } else if response.data() != PROBE {
+                return Err(Error::InvalidRead);
+            }
+        }
+        Err(Error::CrcAlgorithmMismatch)
+    }
+
+    /// Reads the hardware and firmware versions of the device (command
+    /// `0x01`).
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidRead` - If the response is not a version string of the
+    ///   expected form (see [`Version`]).
+    pub fn version(&mut self) -> Result<Version, Error> {
+        let response = self.transact(&Packet::new(0x01, &[]))?;
+        Version::from_data(response.data()).ok_or_else(|| {
+            log::warn!("cannot parse version from {:?}", response);
+            Error::InvalidRead
+        })
+    }
+
+    /// Brings a newly opened connection into a known state, and identifies
+    /// the device.
+    ///
+    /// This is opt-in; [`Device::new`] and [`DeviceBuilder::open`] do not
+    /// call it. The steps are:
+    ///
+    /// 1. Discard any pending input, such as the rest of a response from a
+    ///    previous session ([`Device::flush_input`]).
+    ///
+    /// 2. Ping the device and check that the payload is echoed back, to make
+    ///    sure it is responding.
+    ///
+    /// 3. Read its version ([`Device::version`]), which is returned.
+    ///
+    /// 4. If `reset_key_reporting` is set, disable all key reports
+    ///    ([`Device::configure_key_reporting`]), so that reports configured by
+    ///    a previous session do not fill the report buffer.
+    ///
+    /// The handshake stops at the first step that fails.
+    ///
+    /// # Errors
+    ///
+    /// - `Io` or `Timeout` - If the device does not respond, e.g. because it
+    ///   is powered off or the wrong port was opened.
+    ///
+    /// - `InvalidRead` - If the ping is not echoed back correctly, or the
+    ///   version response cannot be parsed. This suggests that something
+    ///   other than a compatible display is connected.
+    ///
+    /// - `ReturnedError` - If the device rejects one of the commands.
+    pub fn connect_handshake(&mut self, reset_key_reporting: bool) -> Result<Version, Error> {
+        const PAYLOAD: &[u8] = b"cfa635 handshake";
+        self.flush_input()?;
+        let echo = self.ping(PAYLOAD)?;
+        if echo != PAYLOAD {
+            log::warn!("ping echoed {:?} instead of {:?}", echo, PAYLOAD);
+            return Err(Error::InvalidRead);
+        }
+        let version = self.version()?;
+        if reset_key_reporting {
+            self.configure_key_reporting(&[], &[])?;
+        }
+        Ok(version)
+    }
+
+    /// Measures the round-trip time of the link by sending `samples` pings
+    /// (at least one) and timing each response.
+    ///
+    /// Each ping carries the maximum payload ([`limits::MAX_PING_LEN`]), so
+    /// the result is close to the time it takes to send and acknowledge a
+    /// typical command.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidRead` - If a response does not echo the ping's payload.
+    pub fn measure_latency(&mut self, samples: usize) -> Result<LatencyStats, Error> {
+        const PAYLOAD: [u8; limits::MAX_PING_LEN] = *b"latency-probe-16";
+        let mut times = Vec::with_capacity(samples.max(1));
+        for _ in 0..samples.max(1) {
+            let start = self.clock.now();
+            let pong = self.ping(&PAYLOAD)?;
+            times.push(self.clock.now() - start);
+            if pong != PAYLOAD {
+                return Err(Error::InvalidRead);
+            }
+        }
+        Ok(LatencyStats::from_samples(&times))
+    }
+
     /// Saves the current state of the device as its "boot" state, i.e., the
     /// state that will be restored when the device powers on.
     ///
@@ -126,25 +862,80 @@ impl Device {
         Ok(())
     }
 
+    /// Writes to the non-volatile user flash area.
+    ///
+    /// The area is [`limits::USER_FLASH_LEN`] bytes long and keeps its
+    /// contents across power cycles. If `data` is shorter than the area, the
+    /// rest of it is filled with zeros.
+    ///
+    /// Note: The flash has a limited number of write cycles, so this is meant
+    /// for things like provisioning, not for frequently changing state.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If `data` is longer than
+    ///   [`limits::USER_FLASH_LEN`]. The error's `field` is `"data"`.
+    pub fn write_user_flash(&mut self, data: &[u8]) -> Result<(), Error> {
+        check_len("data", data, limits::USER_FLASH_LEN)?;
+        let mut contents = [0; limits::USER_FLASH_LEN];
+        contents[..data.len()].copy_from_slice(data);
+        self.transact(&Packet::new(0x02, &contents))?;
+        Ok(())
+    }
+
+    /// Reads the contents of the non-volatile user flash area.
+    ///
+    /// Always returns [`limits::USER_FLASH_LEN`] bytes.
+    pub fn read_user_flash(&mut self) -> Result<Vec<u8>, Error> {
+        let response = self.transact(&Packet::new(0x03, &[]))?;
+        Ok(response.data().to_vec())
+    }
+
+    /// Reboots the device.
+    ///
+    /// The device resets to its boot state (see [`Device::save_boot_state`]),
+    /// so everything this `Device` knows about the screen is forgotten, as
+    /// with [`Device::invalidate_cache`].
+    ///
+    /// The device may reboot before its response arrives, so a timeout while
+    /// waiting for it is not treated as an error. On some hosts the USB
+    /// serial port also disappears for a moment during the reboot; if later
+    /// commands fail, drop this `Device` and reconnect (e.g. with
+    /// [`Device::open_with_retry`]).
+    pub fn reboot(&mut self) -> Result<(), Error> {
+        // The datasheet's magic sequence for rebooting the CFA635 itself.
+        const REBOOT: [u8; 3] = [8, 18, 99];
+        let result = self.transact(&Packet::new(0x05, &REBOOT));
+        self.invalidate_cache();
+        match result {
+            Ok(_) | Err(Error::Timeout) => Ok(()),
+            Err(Error::Io(err)) if err.kind() == std::io::ErrorKind::TimedOut => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Fills the screen with empty / space characters, and moves the cursor to
     /// the top-left character (row 0, column 0).
     pub fn clear_screen(&mut self) -> Result<(), Error> {
         self.transact(&Packet::new(0x06, &[]))?;
+        self.cache.cursor_position = Some((0, 0));
+        self.shadow = Screen::blank();
+        self.shadow_known = true;
         Ok(())
     }
 
     /// Set the text on a region on the LCD screen, starting at the given position.
     ///
-    /// If the text would be written past the right edge, it will be
-    /// hard-wrapped to the next line.
+    /// The text must fit between `col` and the right edge of the row, so it
+    /// is at most [`limits::MAX_TEXT_LEN`] bytes long. Text that ends exactly
+    /// at the right edge (`col + text.len() == NUM_COLUMNS`) is accepted and
+    /// written in full, and nothing wraps onto the next row. Use
+    /// [`Device::set_text_truncating`] to clip text that is too long instead.
     ///
     /// If there is already text at the given region, it will be overwritten.
     /// Any text outside of the region will be unaffected.
     ///
-    /// Note: The maximum size of `text` is 20 bytes. If more bytes are passed,
-    /// only the first 20 are written.
-    ///
-    /// Note 2: The display does not support arbitrary UTF-8. It is compatible
+    /// Note: The display does not support arbitrary UTF-8. It is compatible
     /// with a subset of ASCII, specifically:
     ///
     /// - Alphanumerics `A-Z` `a-z'` and `0-9`
@@ -161,247 +952,4145 @@ impl Device {
     /// # Errors
     ///
     /// - `InvalidArgument` - If the row or column index is out of bounds (as
-    /// defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]).
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]), or if the text does
+    ///   not fit between `col` and the right edge of the row. The error's
+    ///   `field` is `"row"`, `"col"` or `"text"` respectively.
     pub fn set_text(&mut self, row: u8, col: u8, text: &[u8]) -> Result<(), Error> {
-        if row >= NUM_ROWS || col >= NUM_COLUMNS {
-            return Err(Error::InvalidArgument);
-        }
-        // 20 bytes at most.
-        let text = &text[..text.len().min(MAX_DATA_LEN - 2)];
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        check_len("text", text, usize::from(NUM_COLUMNS - col))?;
 
         let mut buffer = [0; MAX_DATA_LEN];
         let len = 2 + text.len();
         buffer[0] = col;
         buffer[1] = row;
-        buffer[2..len].copy_from_slice(&text);
+        buffer[2..len].copy_from_slice(text);
         self.transact(&Packet::new(0x1f, &buffer[..len]))?;
+        let start = usize::from(col);
+        self.shadow.rows[usize::from(row)][start..start + text.len()].copy_from_slice(text);
         Ok(())
     }
 
-    /// Sets the cursor position to the character at the given row and column.
+    /// Like [`Device::set_text`], but text that does not fit between `col` and
+    /// the right edge of the row is cut off instead of being an error.
+    ///
+    /// This clips the text to its row: the first `NUM_COLUMNS - col` bytes
+    /// are written, so the last one lands in the rightmost column, and the
+    /// rest are discarded. Nothing is ever written to the following row.
     ///
     /// # Errors
     ///
     /// - `InvalidArgument` - If the row or column index is out of bounds (as
-    /// defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]).
-    pub fn set_cursor_position(&mut self, row: u8, col: u8) -> Result<(), Error> {
-        if row >= NUM_ROWS || col >= NUM_COLUMNS {
-            return Err(Error::InvalidArgument);
-        }
-        self.transact(&Packet::new(0x0b, &[col, row]))?;
-        Ok(())
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]). The error's `field` is
+    ///   `"row"` or `"col"` respectively.
+    pub fn set_text_truncating(&mut self, row: u8, col: u8, text: &[u8]) -> Result<(), Error> {
+        check_range("col", col, NUM_COLUMNS)?;
+        let width = usize::from(NUM_COLUMNS - col);
+        self.set_text(row, col, &text[..text.len().min(width)])
     }
 
-    /// Set the cursor style.
-    pub fn set_cursor_style(&mut self, style: CursorStyle) -> Result<(), Error> {
-        self.transact(&Packet::new(0x0c, &[style as u8]))?;
-        Ok(())
+    /// Like [`Device::set_text`], but first replaces control bytes (such as
+    /// tabs and newlines) with spaces.
+    ///
+    /// Bytes below `0x20` show glyphs from the character ROM, so passing
+    /// arbitrary strings to [`Device::set_text`] can produce garbled output.
+    /// Custom characters (bytes below [`limits::CGRAM_SLOTS`]) are kept. See
+    /// [`charset::replace_control_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Device::set_text`].
+    pub fn set_text_sanitized(&mut self, row: u8, col: u8, text: &[u8]) -> Result<(), Error> {
+        self.set_text(row, col, &charset::replace_control_bytes(text))
     }
 
-    /// Set the contrast of the LCD display.
+    /// Like [`Device::set_text`], but takes a [`Position`].
     ///
-    /// From the datasheet:
+    /// # Errors
     ///
-    /// - 60 = light
-    /// - 120 = about right
-    /// - 150 = dark
-    /// - 151-254 = very dark (may be useful at cold temperatures)
-    pub fn set_contrast(&mut self, contrast: u8) -> Result<(), Error> {
-        // Clamp to allowed values:
-        let contrast = contrast.min(254);
-        self.transact(&Packet::new(0x0d, &[contrast]))?;
-        Ok(())
+    /// - `InvalidArgument` - If the text does not fit between the position
+    ///   and the right edge of the row. The error's `field` is `"text"`.
+    pub fn set_text_at(&mut self, position: Position, text: &[u8]) -> Result<(), Error> {
+        self.set_text(position.row, position.col, text)
     }
 
-    /// Set the backlight brightness of the screen and keypad.
+    /// Like [`Device::set_text`], but takes a string and converts it to the
+    /// display's character set with [`charset::encode_char`].
     ///
-    /// The maximum brightness value is 100. Values above this will be
-    /// interpeted as max brightness.
+    /// Unlike [`charset::encode`], characters that the display cannot show are
+    /// an error instead of being replaced by a placeholder. Use
+    /// [`Device::set_text`] for byte-level control, e.g. to show custom
+    /// characters.
     ///
-    /// From the datasheet:
+    /// # Errors
     ///
-    /// - 0 = off
-    /// - 1-100 = variable brightness
-    pub fn set_backlight(&mut self, screen: u8, keypad: u8) -> Result<(), Error> {
-        // Clamp to allowed values:
-        let screen = screen.min(100);
-        let keypad = keypad.min(100);
-        self.transact(&Packet::new(0x0e, &[screen, keypad]))?;
+    /// - `InvalidArgument` - If the row or column index is out of bounds (as
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]), or if the text does
+    ///   not fit between `col` and the right edge of the row. The error's
+    ///   `field` is `"row"`, `"col"` or `"text"` respectively.
+    ///
+    /// - `UnmappableCharacter` - If the text contains a character the display
+    ///   cannot show. The error holds the first such character.
+    pub fn set_str(&mut self, row: u8, col: u8, text: &str) -> Result<(), Error> {
+        self.set_text(row, col, &encode_str(text)?)
+    }
+
+    /// Clears the screen and writes `text` to it, one line per row.
+    ///
+    /// The text is split into lines as with [`str::lines`], so a trailing
+    /// newline does not count as an extra line. Each line is written at the
+    /// start of its row, converted as with [`Device::set_str`]. Empty lines
+    /// leave their row blank.
+    ///
+    /// The whole text is checked before anything is sent, so an invalid text
+    /// leaves the screen unchanged.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), cfa635::Error> {
+    /// # let mut device = cfa635::Device::new("/dev/ttyACM0")?;
+    /// device.set_screen("Backup complete\n\n12 files copied\n0 errors")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the text has more lines than [`NUM_ROWS`], or
+    ///   if a line is longer than [`NUM_COLUMNS`]. The error's `field` is
+    ///   `"lines"` (with the number of lines as its `value`) or `"text"`.
+    ///
+    /// - `UnmappableCharacter` - If the text contains a character the display
+    ///   cannot show.
+    pub fn set_screen(&mut self, text: &str) -> Result<(), Error> {
+        let lines = text
+            .lines()
+            .map(encode_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if lines.len() > usize::from(NUM_ROWS) {
+            return Err(Error::InvalidArgument {
+                field: "lines",
+                value: lines.len(),
+                min: 0,
+                max: NUM_ROWS.into(),
+            });
+        }
+        for line in &lines {
+            check_len("text", line, limits::MAX_TEXT_LEN)?;
+        }
+        self.clear_screen()?;
+        for (row, line) in (0..).zip(&lines) {
+            if !line.is_empty() {
+                self.set_text(row, 0, line)?;
+            }
+        }
         Ok(())
     }
 
-    /// Configure which key events should be reported by the device.
+    /// Shows the current window of `marquee` on its row, then scrolls it left
+    /// by one column for the next call.
     ///
-    /// Any key code that is present in `press` or `release` will be "enabled"
-    /// and will be reported for the respective event. Any key code not present
-    /// will likewise be "disabled".
-    pub fn configure_key_reporting(&mut self, press: &[Key], release: &[Key]) -> Result<(), Error> {
-        let press_mask = press.iter().map(Key::mask).fold(0, |a, b| a | b);
-        let release_mask = release.iter().map(Key::mask).fold(0, |a, b| a | b);
-        self.transact(&Packet::new(0x17, &[press_mask, release_mask]))?;
+    /// The whole row is overwritten. Call this periodically to animate the
+    /// marquee; the scrolling speed is up to the caller.
+    pub fn step_marquee(&mut self, marquee: &mut Marquee) -> Result<(), Error> {
+        self.set_text(marquee.row(), 0, &marquee.window())?;
+        marquee.advance();
         Ok(())
     }
 
-    /// Returns the next report packet, or `None` if there are none available
-    /// right now.
-    pub fn poll_report(&mut self) -> Result<Option<Report>, Error> {
-        if let Some(report) = self.report_buffer.pop_front() {
-            Ok(Some(report))
-        } else {
-            while self.codec.inner().bytes_to_read()? > 0 {
-                let packet = self.recv()?;
-                if let Some(report) = Report::from_raw(&packet) {
-                    return Ok(Some(report));
+    /// Writes the same text to every row of the screen.
+    ///
+    /// The text is padded with spaces to the full width of the screen, so any
+    /// existing content is overwritten.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the text is longer than [`NUM_COLUMNS`]. The
+    ///   error's `field` is `"text"`.
+    pub fn fill_rows(&mut self, text: &[u8]) -> Result<(), Error> {
+        check_len("text", text, NUM_COLUMNS.into())?;
+        let mut line = [b' '; NUM_COLUMNS as usize];
+        line[..text.len()].copy_from_slice(text);
+        for row in 0..NUM_ROWS {
+            self.set_text(row, 0, &line)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the characters currently shown on the display.
+    ///
+    /// The display memory (DDRAM) is read 8 bytes at a time with the "Read 8
+    /// Bytes of LCD Memory" command (`0x0A`), taking 10 commands in total.
+    /// The rows are not contiguous in DDRAM: rows 0 and 2 occupy addresses
+    /// `0x00..0x28`, and rows 1 and 3 occupy `0x40..0x68`.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidRead` - If a response is for a different address than was
+    ///   requested.
+    pub fn read_screen(&mut self) -> Result<Screen, Error> {
+        const CHUNK_LEN: u8 = 8;
+        let mut screen = Screen::blank();
+        for region in [0x00, 0x40] {
+            for offset in (0..2 * NUM_COLUMNS).step_by(CHUNK_LEN.into()) {
+                let address = region + offset;
+                // DDRAM addresses are requested with bit 7 set.
+                let response = self.transact(&Packet::new(0x0a, &[0x80 | address]))?;
+                let (&echo, bytes) = response.data().split_first().ok_or(Error::InvalidRead)?;
+                if echo != 0x80 | address {
+                    log::warn!("requested address {:#04x}, got {:?}", address, response);
+                    return Err(Error::InvalidRead);
+                }
+                for (i, &byte) in (0..).zip(bytes) {
+                    if let Some((row, col)) = screen::ddram_position(address + i) {
+                        screen.rows[usize::from(row)][usize::from(col)] = byte;
+                    }
                 }
             }
-            Ok(None)
         }
+        self.shadow = screen.clone();
+        self.shadow_known = true;
+        Ok(screen)
     }
 
-    /// Set the state of an indicator LED.
+    /// Returns the characters on the display, as recorded by this `Device`.
     ///
-    /// The brightness of the red and green components is a value between 0
-    /// (off) and 100 (max brightness). A value higher than 100 will be
-    /// interpreted as max brightness.
+    /// This is tracked on the host without communicating with the device.
+    /// Text written with [`Device::set_text`] (and the methods built on it)
+    /// and [`Device::clear_screen`] is recorded, and
+    /// [`Device::read_screen`] replaces the record with what was read back.
+    /// The record starts out blank, so it does not include whatever the
+    /// display showed before connecting, nor changes made by raw commands
+    /// (e.g. [`Device::send_no_wait`]) or by other programs. It is reset to
+    /// blank by [`Device::invalidate_cache`] and [`Device::reboot`].
+    pub fn screen_snapshot(&self) -> &[[u8; NUM_COLUMNS as usize]; NUM_ROWS as usize] {
+        self.shadow.rows()
+    }
+
+    /// Replaces the contents of an entire row, like the "Set LCD Contents,
+    /// Line 1/2" commands of older Crystalfontz modules.
+    ///
+    /// This eases porting code written against those commands. The text is
+    /// padded with spaces to the full width of the screen, so the rest of the
+    /// row is cleared.
+    ///
+    /// Note: The CFA635 firmware does not implement the legacy commands (`0x07`
+    /// and `0x08`), so this is always sent as [`Device::set_text`] at column
+    /// 0. Unlike the legacy commands, any of the [`NUM_ROWS`] rows can be set.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the row index is out of bounds (as defined by
+    ///   [`NUM_ROWS`]), or the text is longer than [`NUM_COLUMNS`]. The
+    ///   error's `field` is `"line"` or `"text"` respectively.
+    pub fn set_line(&mut self, line: u8, text: &[u8]) -> Result<(), Error> {
+        check_range("line", line, NUM_ROWS)?;
+        check_len("text", text, NUM_COLUMNS.into())?;
+        let mut row = [b' '; NUM_COLUMNS as usize];
+        row[..text.len()].copy_from_slice(text);
+        self.set_text(line, 0, &row)
+    }
+
+    /// Briefly shows a message (e.g. `b"SAVED"`) over the current screen,
+    /// and then restores what was there before.
+    ///
+    /// The message is centered on row 1, the upper of the two middle rows,
+    /// and the rest of that row is blanked while it is shown. After
+    /// `duration`, the row is restored from [`Device::screen_snapshot`]. If
+    /// the contents of the display are not known yet, e.g. right after
+    /// connecting, they are read back first with [`Device::read_screen`].
+    ///
+    /// Note: This blocks the calling thread for `duration`. Reports that
+    /// arrive in the meantime are buffered when the next command is sent,
+    /// as usual.
     ///
     /// # Errors
     ///
-    /// - `InvalidArgument` - If the LED index is out of bounds (as
-    /// defined by [`NUM_LEDS`]).
-    pub fn set_led(&mut self, index: u8, red: u8, green: u8) -> Result<(), Error> {
-        if index >= NUM_LEDS {
-            return Err(Error::InvalidArgument);
+    /// - `InvalidArgument` - If the text is longer than [`NUM_COLUMNS`]. The
+    ///   error's `field` is `"text"`.
+    ///
+    /// - `InvalidRead` - If the display has to be read back and that fails
+    ///   (see [`Device::read_screen`]). The message is not shown in that case.
+    pub fn flash_message(&mut self, text: &[u8], duration: Duration) -> Result<(), Error> {
+        const ROW: u8 = (NUM_ROWS - 1) / 2;
+        check_len("text", text, NUM_COLUMNS.into())?;
+        if !self.shadow_known {
+            self.read_screen()?;
         }
-        let (red_gpio, green_gpio) = match index {
-            0 => (12, 11),
-            1 => (10, 9),
-            2 => (8, 7),
-            3 => (6, 5),
-            _ => unreachable!(),
-        };
-        self.transact(&Packet::new(0x22, &[red_gpio, red]))?;
-        self.transact(&Packet::new(0x22, &[green_gpio, green]))?;
-        Ok(())
+        let saved = self.shadow.rows[usize::from(ROW)];
+        let mut message = [b' '; NUM_COLUMNS as usize];
+        let start = (message.len() - text.len()) / 2;
+        message[start..start + text.len()].copy_from_slice(text);
+        self.set_text(ROW, 0, &message)?;
+        self.clock.sleep(duration);
+        self.set_text(ROW, 0, &saved)
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-#[non_exhaustive]
-pub enum CursorStyle {
-    NoCursor = 0,
-    BlinkingBlock = 1,
-    StaticUnderscore = 2,
-    BlinkingUnderscore = 3,
-}
+    /// Shows the last lines read from `reader` on the top `lines` rows of the
+    /// screen, scrolling up as new lines arrive, like `tail -f`.
+    ///
+    /// Runs until `reader` reaches end-of-file. Only rows whose contents
+    /// change are rewritten. Lines are decoded as UTF-8 (invalid sequences are
+    /// replaced) and converted with [`charset::encode`]. Lines longer than
+    /// [`NUM_COLUMNS`] are handled according to `overflow`.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If `lines` is zero or greater than [`NUM_ROWS`].
+    ///   The error's `field` is `"lines"`.
+    ///
+    /// - `Io` - If reading from `reader` fails.
+    pub fn tail(
+        &mut self,
+        mut reader: impl BufRead,
+        lines: usize,
+        overflow: LineOverflow,
+    ) -> Result<(), Error> {
+        check_bounds("lines", lines, 1, NUM_ROWS.into())?;
+        let width = usize::from(NUM_COLUMNS);
+        let blank = [b' '; NUM_COLUMNS as usize];
+        let mut shown: Vec<Option<[u8; NUM_COLUMNS as usize]>> = vec![None; lines];
+        let mut history: VecDeque<[u8; NUM_COLUMNS as usize]> = VecDeque::with_capacity(lines);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                return Ok(());
+            }
+            let line = String::from_utf8_lossy(&buf);
+            let encoded = charset::encode(line.trim_end_matches(['\r', '\n']));
+            let chunks: Vec<&[u8]> = match overflow {
+                LineOverflow::Truncate => vec![&encoded[..encoded.len().min(width)]],
+                LineOverflow::Wrap if encoded.is_empty() => vec![&[]],
+                LineOverflow::Wrap => encoded.chunks(width).collect(),
+            };
+            for chunk in chunks {
+                if history.len() == lines {
+                    history.pop_front();
+                }
+                let mut row = blank;
+                row[..chunk.len()].copy_from_slice(chunk);
+                history.push_back(row);
+            }
+            for (row, (text, shown)) in (0..).zip(history.iter().zip(&mut shown)) {
+                if *shown != Some(*text) {
+                    self.set_text(row, 0, text)?;
+                    *shown = Some(*text);
+                }
+            }
+        }
+    }
 
-#[derive(Debug, Clone)]
-pub enum Report {
-    KeyActivity { key: Key, pressed: bool },
-}
+    /// Defines one of the custom characters.
+    ///
+    /// Each byte of `bitmap` is one row of pixels, from top to bottom. The low
+    /// [`limits::CUSTOM_CHAR_WIDTH`] bits are the pixels, with the most
+    /// significant of them on the left; higher bits are ignored. The
+    /// [`charset`] module has helpers for building bitmaps, like the
+    /// [`glyph!`] macro.
+    ///
+    /// Once defined, the character is shown wherever byte `index` appears in
+    /// text, e.g. with [`Device::set_text`]. Characters that are already on
+    /// the screen change immediately.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the index is out of bounds (as defined by
+    ///   [`limits::CGRAM_SLOTS`]). The error's `field` is `"index"`.
+    pub fn set_custom_character(
+        &mut self,
+        index: u8,
+        bitmap: charset::GlyphBitmap,
+    ) -> Result<(), Error> {
+        check_range("index", index, limits::CGRAM_SLOTS)?;
+        let mut data = [0; 1 + limits::CUSTOM_CHAR_HEIGHT as usize];
+        data[0] = index;
+        data[1..].copy_from_slice(&bitmap);
+        self.transact(&Packet::new(0x09, &data))?;
+        Ok(())
+    }
 
-impl Report {
-    pub fn from_raw(packet: &Packet) -> Option<Self> {
-        match packet.packet_type() {
-            0x80 => {
-                let data = match packet.data().get(0) {
-                    Some(&x) => x,
-                    None => {
-                        log::warn!("not enough bytes for a key activity report");
-                        return None;
-                    }
-                };
-                let (key, pressed) = match data {
-                    1 => (Key::Up, true),
-                    2 => (Key::Down, true),
-                    3 => (Key::Left, true),
-                    4 => (Key::Right, true),
-                    5 => (Key::Enter, true),
-                    6 => (Key::Exit, true),
-                    7 => (Key::Up, false),
-                    8 => (Key::Down, false),
-                    9 => (Key::Left, false),
-                    10 => (Key::Right, false),
-                    11 => (Key::Enter, false),
-                    12 => (Key::Exit, false),
-                    _ => {
-                        log::warn!("unknown key code {:?}", data);
-                        return None;
-                    }
-                };
-                Some(Self::KeyActivity { key, pressed })
-            }
-            _ => None,
+    /// Reads back one of the custom characters from the display's character
+    /// generator memory (CGRAM), with the "Read 8 Bytes of LCD Memory"
+    /// command (`0x0A`).
+    ///
+    /// Only the low [`limits::CUSTOM_CHAR_WIDTH`] bits of each row are
+    /// pixels, so the higher bits are cleared in the result. A bitmap passed
+    /// to [`Device::set_custom_character`] reads back the same once its
+    /// higher bits are cleared.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the index is out of bounds (as defined by
+    ///   [`limits::CGRAM_SLOTS`]). The error's `field` is `"index"`.
+    ///
+    /// - `InvalidRead` - If the response is for a different address than was
+    ///   requested.
+    pub fn read_custom_character(&mut self, index: u8) -> Result<charset::GlyphBitmap, Error> {
+        check_range("index", index, limits::CGRAM_SLOTS)?;
+        // CGRAM addresses are requested with bit 6 set.
+        let address = 0x40 | (index * limits::CUSTOM_CHAR_HEIGHT);
+        let response = self.transact(&Packet::new(0x0a, &[address]))?;
+        let (&echo, bytes) = response.data().split_first().ok_or(Error::InvalidRead)?;
+        if echo != address {
+            log::warn!("requested address {:#04x}, got {:?}", address, response);
+            return Err(Error::InvalidRead);
         }
+        let mut bitmap = [0; limits::CUSTOM_CHAR_HEIGHT as usize];
+        for (row, &byte) in bitmap.iter_mut().zip(bytes) {
+            *row = byte & GLYPH_PIXEL_MASK;
+        }
+        Ok(bitmap)
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Key {
-    Up,
-    Down,
-    Left,
-    Right,
-    Enter,
-    Exit,
-}
+    /// Sets the cursor position to the character at the given row and column.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the row or column index is out of bounds (as
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]). The error's `field` is
+    ///   `"row"` or `"col"` respectively.
+    pub fn set_cursor_position(&mut self, row: u8, col: u8) -> Result<(), Error> {
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        self.transact(&Packet::new(0x0b, &[col, row]))?;
+        self.cache.cursor_position = Some((row, col));
+        Ok(())
+    }
 
-impl Key {
-    fn mask(&self) -> u8 {
-        match self {
-            Self::Up => 0x01,
-            Self::Enter => 0x02,
-            Self::Exit => 0x04,
-            Self::Left => 0x08,
-            Self::Right => 0x10,
-            Self::Down => 0x20,
+    /// Like [`Device::set_cursor_position`], but takes a [`Position`].
+    pub fn set_cursor_position_at(&mut self, position: Position) -> Result<(), Error> {
+        self.set_cursor_position(position.row, position.col)
+    }
+
+    /// Returns the cursor position as `(row, col)`, or `None` if it is not
+    /// known.
+    ///
+    /// The position is tracked on the host, and is known after a call to
+    /// [`Device::set_cursor_position`] or [`Device::clear_screen`] (which
+    /// moves the cursor to `(0, 0)`). It is not read from the device.
+    pub fn cursor_position(&self) -> Option<(u8, u8)> {
+        self.cache.cursor_position
+    }
+
+    /// Moves the cursor by the given number of rows and columns, relative to
+    /// its current position (see [`Device::cursor_position`]).
+    ///
+    /// Positive values move down and right. The new position is clamped to
+    /// the screen, so moving past an edge stops at that edge.
+    ///
+    /// # Errors
+    ///
+    /// - `UnknownCursorPosition` - If the cursor position is not known.
+    pub fn move_cursor(&mut self, drow: i8, dcol: i8) -> Result<(), Error> {
+        let (row, col) = self
+            .cache
+            .cursor_position
+            .ok_or(Error::UnknownCursorPosition)?;
+        let offset = |pos: u8, delta: i8, limit: u8| {
+            (i16::from(pos) + i16::from(delta)).clamp(0, i16::from(limit) - 1) as u8
+        };
+        self.set_cursor_position(offset(row, drow, NUM_ROWS), offset(col, dcol, NUM_COLUMNS))
+    }
+
+    /// Set the cursor style.
+    pub fn set_cursor_style(&mut self, style: CursorStyle) -> Result<(), Error> {
+        self.transact(&Packet::new(0x0c, &[style as u8]))?;
+        if style != CursorStyle::NoCursor {
+            self.cursor_style = Some(style);
         }
+        Ok(())
     }
-}
 
-#[derive(Debug, Error)]
-#[non_exhaustive]
-pub enum Error {
-    #[error("serialport: {0}")]
-    SerialPort(#[from] serialport::Error),
+    /// Shows or hides the cursor.
+    ///
+    /// Hiding sets the style to [`CursorStyle::NoCursor`]. Showing restores
+    /// the last visible style set on this connection (via
+    /// [`Device::set_cursor_style`]), or [`CursorStyle::BlinkingBlock`] if
+    /// none has been set yet.
+    pub fn set_cursor_visible(&mut self, visible: bool) -> Result<(), Error> {
+        let style = if visible {
+            self.cursor_style.unwrap_or(CursorStyle::BlinkingBlock)
+        } else {
+            CursorStyle::NoCursor
+        };
+        self.set_cursor_style(style)
+    }
 
-    #[error("io: {0}")]
-    Io(#[from] std::io::Error),
+    /// Set the contrast of the LCD display.
+    ///
+    /// From the datasheet:
+    ///
+    /// - 60 = light
+    /// - 120 = about right
+    /// - 150 = dark
+    /// - 151-254 = very dark (may be useful at cold temperatures)
+    ///
+    /// Values above [`limits::MAX_CONTRAST`] are interpreted as the maximum.
+    /// Note that contrast is not a percentage; use a [`Contrast`] to check
+    /// the value up front, or for the named levels.
+    ///
+    /// Passing `None` leaves the contrast unchanged: the last known value is
+    /// sent again, or nothing is sent if the value is not known yet. Values
+    /// are always sent to the device, even if they match the last known
+    /// value.
+    pub fn set_contrast(&mut self, contrast: impl Into<Option<u8>>) -> Result<(), Error> {
+        let contrast = match contrast.into().or(self.cache.contrast) {
+            // Clamp to allowed values:
+            Some(contrast) => contrast.min(limits::MAX_CONTRAST),
+            None => return Ok(()),
+        };
+        self.transact(&Packet::new(0x0d, &[contrast]))?;
+        self.cache.contrast = Some(contrast);
+        Ok(())
+    }
 
-    /// Read an unexpected/incorrect byte, either because of an incompatibility
-    /// or desync.
-    #[error("invalid read - potential desync on serial port")]
-    InvalidRead,
+    /// Sets the contrast appropriate for the given ambient temperature, using
+    /// the device's contrast curve.
+    ///
+    /// The temperature may come from any source, like a temperature sensor
+    /// attached to the device. See [`ContrastCurve`] for the default curve,
+    /// and [`Device::set_contrast_curve`] to replace it.
+    pub fn set_contrast_for_temperature(&mut self, celsius: f32) -> Result<(), Error> {
+        let contrast = self.contrast_curve.contrast_at(celsius);
+        self.set_contrast(contrast)
+    }
 
-    /// An argument to a function call had a value that was out of range.
+    /// Replaces the curve used by [`Device::set_contrast_for_temperature`].
+    pub fn set_contrast_curve(&mut self, curve: ContrastCurve) {
+        self.contrast_curve = curve;
+    }
+
+    /// Set the backlight brightness of the screen and keypad.
+    ///
+    /// The maximum brightness value is 100. Values above this will be
+    /// interpeted as max brightness.
+    ///
+    /// From the datasheet:
+    ///
+    /// - 0 = off
+    /// - 1-100 = variable brightness
+    ///
+    /// Passing `None` for either value leaves that backlight unchanged. Since
+    /// both values are set by the same command, the last known value is sent
+    /// again; if it is not known yet, it is read from the device first (see
+    /// [`Device::read_status`]). If both are `None` and neither value is
+    /// known, nothing is sent. Values are always sent to the device, even if
+    /// they match the last known values.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidRead` - If an unchanged value had to be read from the device,
+    ///   and the device did not report it.
+    pub fn set_backlight(
+        &mut self,
+        screen: impl Into<Option<u8>>,
+        keypad: impl Into<Option<u8>>,
+    ) -> Result<(), Error> {
+        let screen = screen.into().or(self.cache.screen_backlight);
+        let keypad = keypad.into().or(self.cache.keypad_backlight);
+        let (screen, keypad) = match (screen, keypad) {
+            (None, None) => return Ok(()),
+            (Some(screen), Some(keypad)) => (screen, keypad),
+            (screen, keypad) => {
+                let status = self.read_status()?;
+                let keypad = keypad.or(status.keypad_backlight);
+                (
+                    screen.unwrap_or(status.backlight),
+                    keypad.ok_or(Error::InvalidRead)?,
+                )
+            }
+        };
+        // Clamp to allowed values:
+        let screen = screen.min(100);
+        let keypad = keypad.min(100);
+        self.transact(&Packet::new(0x0e, &[screen, keypad]))?;
+        self.cache.screen_backlight = Some(screen);
+        self.cache.keypad_backlight = Some(keypad);
+        Ok(())
+    }
+
+    /// Set the screen and keypad backlights to the same brightness.
     ///
-    /// See the individual functions' documentation for more details about
-    /// allowed values of their arguments.
-    #[error("invalid value for argument")]
-    InvalidArgument,
+    /// Shorthand for `set_backlight(brightness, brightness)`; see
+    /// [`Device::set_backlight`].
+    pub fn set_brightness(&mut self, brightness: u8) -> Result<(), Error> {
+        self.set_backlight(brightness, brightness)
+    }
 
-    /// The device returned an error in its response to our command.
+    /// Set the backlight brightness of the screen, leaving the keypad
+    /// unchanged.
     ///
-    /// The cause is usually an error that is out of our control, e.g.
-    /// [`Device::save_boot_state`] may return this error if the device doesn't
-    /// read back the correct data after saving, which is unlikely but may
-    /// eventually happen because of a worn-out flash.
-    #[error("Device returned an error response")]
-    ReturnedError,
-}
+    /// Shorthand for `set_backlight(brightness, None)`; see
+    /// [`Device::set_backlight`].
+    pub fn set_screen_backlight(&mut self, brightness: u8) -> Result<(), Error> {
+        self.set_backlight(brightness, None)
+    }
 
-impl From<WritePacketError> for Error {
-    fn from(err: WritePacketError) -> Self {
-        match err {
-            WritePacketError::Io(err) => Self::Io(err),
+    /// Set the backlight brightness of the keypad, leaving the screen
+    /// unchanged.
+    ///
+    /// Shorthand for `set_backlight(None, brightness)`; see
+    /// [`Device::set_backlight`].
+    pub fn set_keypad_backlight(&mut self, brightness: u8) -> Result<(), Error> {
+        self.set_backlight(None, brightness)
+    }
+
+    /// Configure which key events should be reported by the device.
+    ///
+    /// Any key code that is present in `press` or `release` will be "enabled"
+    /// and will be reported for the respective event. Any key code not present
+    /// will likewise be "disabled".
+    pub fn configure_key_reporting(&mut self, press: &[Key], release: &[Key]) -> Result<(), Error> {
+        let press_mask = press.iter().map(Key::mask).fold(0, |a, b| a | b);
+        let release_mask = release.iter().map(Key::mask).fold(0, |a, b| a | b);
+        self.transact(&Packet::new(0x17, &[press_mask, release_mask]))?;
+        self.key_reporting = Some(KeyReporting::from_masks(press_mask, release_mask));
+        Ok(())
+    }
+
+    /// Returns which key events are currently reported by the device.
+    ///
+    /// The configuration is read from the device using
+    /// [`Device::read_status`]. If the firmware does not support that command,
+    /// this falls back to the last configuration set by
+    /// [`Device::configure_key_reporting`] on this connection.
+    pub fn key_reporting_config(&mut self) -> Result<KeyReporting, Error> {
+        match self.read_status() {
+            Ok(status) => Ok(KeyReporting::from_masks(
+                status.key_press_mask,
+                status.key_release_mask,
+            )),
+            Err(err @ (Error::InvalidRead | Error::ReturnedError { .. })) => {
+                match &self.key_reporting {
+                    Some(config) => {
+                        log::debug!("status not available, using cached key reporting config");
+                        Ok(config.clone())
+                    }
+                    None => Err(err),
+                }
+            }
+            Err(err) => Err(err),
         }
     }
-}
 
-impl From<ReadPacketError> for Error {
-    fn from(err: ReadPacketError) -> Self {
-        match err {
-            ReadPacketError::Io(err) => Self::Io(err),
-            ReadPacketError::InvalidPacket => Self::InvalidRead,
+    /// Reads the state of the keypad (command `0x18`).
+    ///
+    /// This works regardless of the key reporting configuration, and does not
+    /// affect the reports returned by [`Device::poll_report`]. Reading the
+    /// keypad clears the press and release flags on the device, so each event
+    /// is only seen by one call.
+    pub fn read_keypad(&mut self) -> Result<KeypadState, Error> {
+        let response = self.transact(&Packet::new(0x18, &[]))?;
+        match *response.data() {
+            [pressed, presses, releases] => Ok(KeypadState {
+                currently_pressed: Key::from_mask(pressed),
+                presses_since_last_poll: Key::from_mask(presses),
+                releases_since_last_poll: Key::from_mask(releases),
+            }),
+            _ => Err(Error::InvalidRead),
+        }
+    }
+
+    /// Returns the next report packet, or `None` if there are none available
+    /// right now.
+    pub fn poll_report(&mut self) -> Result<Option<Report>, Error> {
+        Ok(self.poll_report_timestamped()?.map(|(_, report)| report))
+    }
+
+    /// Like [`Device::poll_report`], but also returns the time the report was
+    /// received from the device.
+    ///
+    /// Reports that were buffered while waiting for a command response keep
+    /// the time they were read, not the time they are returned.
+    pub fn poll_report_timestamped(&mut self) -> Result<Option<(Instant, Report)>, Error> {
+        let entry = match self.report_buffer.pop_front() {
+            Some(entry) => Some(entry),
+            None => self.read_report()?,
+        };
+        if let Some((_, Report::KeyActivity { key, pressed })) = &entry {
+            if *pressed {
+                self.held_keys |= key.mask();
+            } else {
+                self.held_keys &= !key.mask();
+            }
         }
+        Ok(entry)
+    }
+
+    /// Returns an iterator over the reports that are available right now.
+    ///
+    /// Each item is what [`Device::poll_report`] would return, so the
+    /// iterator never blocks: it ends as soon as no more reports are
+    /// available, and can be called again later to pick up new ones. It also
+    /// ends after yielding an error.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), cfa635::Error> {
+    /// # let mut device = cfa635::Device::new("/dev/ttyACM0")?;
+    /// for report in device.reports() {
+    ///     println!("{:?}", report?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reports(&mut self) -> impl Iterator<Item = Result<Report, Error>> + '_ {
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let next = self.poll_report().transpose();
+            failed = matches!(next, Some(Err(_)));
+            next
+        })
+    }
+
+    /// Reads packets that are already available until a report is found.
+    fn read_report(&mut self) -> Result<Option<(Instant, Report)>, Error> {
+        while self.codec.buffered_len() > 0 || self.codec.inner().bytes_to_read()? > 0 {
+            let packet = self.recv()?;
+            if let Some(report) = Report::from_raw(&packet) {
+                return Ok(Some((self.clock.now(), report)));
+            }
+            log::debug!("discarding non-report packet {:?}", packet);
+            self.discarded_packets += 1;
+        }
+        Ok(None)
+    }
+
+    /// Returns how many packets other than reports were discarded while
+    /// polling for reports.
+    ///
+    /// These are usually responses to commands sent with
+    /// [`Device::send_no_wait`], so this can be used to check that all of
+    /// those responses have been drained.
+    pub fn discarded_packets(&self) -> u64 {
+        self.discarded_packets
+    }
+
+    /// Discards all reports that have been received but not consumed yet,
+    /// including those still waiting in the port's input buffer. Returns the
+    /// number of reports that were discarded.
+    ///
+    /// This is useful right after connecting, to ignore key presses that
+    /// happened before the application was ready (see [`Device::new`]).
+    pub fn discard_reports(&mut self) -> Result<usize, Error> {
+        let mut count = self.report_buffer.len();
+        self.report_buffer.clear();
+        while self.read_report()?.is_some() {
+            count += 1;
+        }
+        log::debug!("discarded {} reports", count);
+        Ok(count)
+    }
+
+    /// Discards all bytes that have been received but not processed yet,
+    /// including partial packets and any bytes waiting in the port's input
+    /// buffer.
+    ///
+    /// This gives a clean slate when the port may hold leftovers from an
+    /// earlier session, e.g. the response to a command sent just before a
+    /// previous instance of the program crashed. It is called automatically
+    /// by [`Device::new`] and [`DeviceBuilder::open`].
+    ///
+    /// Reports that were already decoded (see [`Device::peek_reports`]) are
+    /// kept; use [`Device::discard_reports`] to drop those too.
+    pub fn flush_input(&mut self) -> Result<(), Error> {
+        let mut discarded = self.codec.clear_buffer();
+        let mut scratch = [0; 64];
+        loop {
+            let available = self.codec.inner().bytes_to_read()? as usize;
+            if available == 0 {
+                break;
+            }
+            let len = available.min(scratch.len());
+            discarded += self.codec.inner_mut().read(&mut scratch[..len])?;
+        }
+        if discarded > 0 {
+            log::debug!("discarded {} bytes of stale input", discarded);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if `key` is currently held down.
+    ///
+    /// This is tracked from the key reports returned by
+    /// [`Device::poll_report`] and related methods, so it only reflects
+    /// reports that have been consumed. It also requires both press and
+    /// release reporting to be enabled for the key (see
+    /// [`Device::configure_key_reporting`]); otherwise the key may appear to
+    /// be held forever, or never.
+    pub fn is_key_held(&self, key: Key) -> bool {
+        self.held_keys & key.mask() != 0
+    }
+
+    /// Returns the reports that have been received but not yet consumed, oldest
+    /// first, without removing them.
+    ///
+    /// This only inspects reports that were already read while waiting for a
+    /// command response; it does not read from the serial port.
+    pub fn peek_reports(&self) -> impl ExactSizeIterator<Item = &Report> + '_ {
+        self.report_buffer.iter().map(|(_, report)| report)
+    }
+
+    /// Waits for the next report, returning `None` if none arrives within
+    /// `timeout`. A timeout of `None` waits indefinitely.
+    ///
+    /// Packets other than reports are discarded, as with
+    /// [`Device::poll_report`]. Reaching the timeout is not an error; only
+    /// errors from the underlying port are returned, and `Interrupted` if the
+    /// wait is cancelled through [`Device::interrupt_handle`].
+    pub fn wait_report(&mut self, timeout: Option<Duration>) -> Result<Option<Report>, Error> {
+        let deadline = timeout.map(|timeout| self.clock.now() + timeout);
+        loop {
+            self.check_interrupted()?;
+            if let Some(report) = self.poll_report()? {
+                return Ok(Some(report));
+            }
+            let mut sleep = POLL_INTERVAL;
+            if let Some(deadline) = deadline {
+                let now = self.clock.now();
+                if now >= deadline {
+                    return Ok(None);
+                }
+                sleep = sleep.min(deadline - now);
+            }
+            self.clock.sleep(sleep);
+        }
+    }
+
+    /// Waits up to `timeout` for the next report, returning `default` if none
+    /// arrives in time.
+    ///
+    /// Reaching the timeout is not an error; only errors from the underlying
+    /// port (see [`Device::poll_report`]) are returned.
+    pub fn next_report_or(&mut self, timeout: Duration, default: Report) -> Result<Report, Error> {
+        Ok(self.wait_report(Some(timeout))?.unwrap_or(default))
+    }
+
+    /// Returns the GPIO pins that drive the red and green components of an
+    /// indicator LED on a CFA635, as `(red, green)`.
+    ///
+    /// These pins are reserved for the LEDs; driving them directly will change
+    /// the LED state. Returns `None` if the LED index is out of bounds (as
+    /// defined by [`NUM_LEDS`]). For other models, see
+    /// [`Model::led_gpio_pins`].
+    pub fn led_gpio_pins(index: u8) -> Option<(u8, u8)> {
+        Model::Cfa635.led_gpio_pins(index)
+    }
+
+    /// Returns the model this device is treated as, which decides the LED
+    /// pins used by [`Device::set_led`] (see [`DeviceBuilder::model`] and
+    /// [`DeviceBuilder::detect_model`]).
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Set the state of an indicator LED.
+    ///
+    /// The brightness of the red and green components is a value between 0
+    /// (off) and 100 (max brightness). A value higher than 100 will be
+    /// interpreted as max brightness.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the LED is given as a `u8` index that is out
+    ///   of bounds (as defined by [`NUM_LEDS`]). The error's `field` is
+    ///   `"index"`.
+    ///
+    /// - `UnsupportedModel` - If the [model](Device::model) has no such LED.
+    pub fn set_led(&mut self, index: impl IntoLed, red: u8, green: u8) -> Result<(), Error> {
+        let index = index.into_led()?.index();
+        let (red_gpio, green_gpio) = self
+            .model
+            .led_gpio_pins(index)
+            .ok_or(Error::UnsupportedModel(self.model))?;
+        self.set_gpio(red_gpio, red)?;
+        self.set_gpio(green_gpio, green)?;
+        let color = LedColor::clamped(red, green);
+        if color != LedColor::OFF {
+            self.led_colors[usize::from(index)] = Some(color);
+        }
+        Ok(())
+    }
+
+    /// Turns on an indicator LED.
+    ///
+    /// If `color` is `None`, the LED is restored to the last color it was lit
+    /// with on this connection (via this method or [`Device::set_led`]), or
+    /// full-brightness green if it has not been lit yet.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the LED is given as a `u8` index that is out
+    ///   of bounds (as defined by [`NUM_LEDS`]). The error's `field` is
+    ///   `"index"`.
+    ///
+    /// - `UnsupportedModel` - If the [model](Device::model) has no such LED.
+    pub fn led_on(
+        &mut self,
+        index: impl IntoLed,
+        color: impl Into<Option<LedColor>>,
+    ) -> Result<(), Error> {
+        let index = index.into_led()?.index();
+        let color = color
+            .into()
+            .or(self.led_colors[usize::from(index)])
+            .unwrap_or(LedColor::GREEN);
+        self.set_led_color(index, color)
+    }
+
+    /// Like [`Device::set_led`], but takes the color as a [`LedColor`].
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the LED is given as a `u8` index that is out
+    ///   of bounds (as defined by [`NUM_LEDS`]). The error's `field` is
+    ///   `"index"`.
+    ///
+    /// - `UnsupportedModel` - If the [model](Device::model) has no such LED.
+    pub fn set_led_color(&mut self, index: impl IntoLed, color: LedColor) -> Result<(), Error> {
+        self.set_led(index, color.red, color.green)
+    }
+
+    /// Turns off an indicator LED.
+    ///
+    /// The LED's last color is remembered, so that it can be restored by
+    /// passing `None` to [`Device::led_on`].
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the LED is given as a `u8` index that is out
+    ///   of bounds (as defined by [`NUM_LEDS`]). The error's `field` is
+    ///   `"index"`.
+    ///
+    /// - `UnsupportedModel` - If the [model](Device::model) has no such LED.
+    pub fn led_off(&mut self, index: impl IntoLed) -> Result<(), Error> {
+        self.set_led(index, 0, 0)
+    }
+
+    /// Sets the colors of all indicator LEDs, from top to bottom.
+    ///
+    /// The commands are sent back-to-back as a [`Device::batch`], so the
+    /// LEDs change at practically the same time instead of one after the
+    /// other.
+    pub fn set_all_leds(&mut self, colors: [LedColor; NUM_LEDS as usize]) -> Result<(), Error> {
+        self.batch(|batch| {
+            for (led, color) in Led::ALL.into_iter().zip(colors) {
+                batch.set_led_color(led, color)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Brings the display into the state described by `frame`.
+    ///
+    /// Only the parts of the frame that differ from previously applied frames
+    /// are sent, back-to-back as a [`Device::batch`]. Parts that are not
+    /// specified by `frame` are left unchanged. If the batch fails, the frame
+    /// is not recorded as applied, so applying it again resends all of its
+    /// changes.
+    ///
+    /// Note: The comparison is made against the frames applied through this
+    /// method only. If the display is changed by other means (e.g.
+    /// [`Device::set_text`]), the affected parts of the next frame may be
+    /// skipped even though they differ from what is shown.
+    pub fn apply_frame(&mut self, frame: &Frame) -> Result<(), Error> {
+        let last = self.last_frame.clone();
+        self.batch(|batch| {
+            for (row, (text, last_text)) in (0..).zip(frame.rows.iter().zip(&last.rows)) {
+                if let Some(text) = text.filter(|text| Some(text) != last_text.as_ref()) {
+                    batch.set_text(row, 0, &text)?;
+                }
+            }
+            if let Some(style) = frame.cursor_style.filter(|&s| Some(s) != last.cursor_style) {
+                batch.set_cursor_style(style)?;
+            }
+            if let Some((row, col)) = frame
+                .cursor_position
+                .filter(|&p| Some(p) != last.cursor_position)
+            {
+                batch.set_cursor_position(row, col)?;
+            }
+            for (index, (color, last_color)) in (0..).zip(frame.leds.iter().zip(&last.leds)) {
+                if let Some(color) = color.filter(|&c| Some(c) != *last_color) {
+                    batch.set_led(index, color.red, color.green)?;
+                }
+            }
+            if let Some((screen, keypad)) = frame.backlight.filter(|&b| Some(b) != last.backlight) {
+                batch.set_backlight(screen, keypad)?;
+            }
+            Ok(())
+        })?;
+
+        let applied = &mut self.last_frame;
+        for (last_text, text) in applied.rows.iter_mut().zip(frame.rows) {
+            *last_text = text.or(*last_text);
+        }
+        applied.cursor_style = frame.cursor_style.or(applied.cursor_style);
+        applied.cursor_position = frame.cursor_position.or(applied.cursor_position);
+        for (last_color, color) in applied.leds.iter_mut().zip(frame.leds) {
+            *last_color = color.or(*last_color);
+        }
+        applied.backlight = frame.backlight.or(applied.backlight);
+        Ok(())
+    }
+
+    /// Sends the contents of `fb` to the display, as [`Device::redraw`] does.
+    pub fn flush_framebuffer(&mut self, fb: &Framebuffer) -> Result<(), Error> {
+        self.redraw(fb.screen())
+    }
+
+    /// Changes the display to show `screen`, without clearing it first.
+    ///
+    /// Only the characters that differ from [`Device::screen_snapshot`] are
+    /// sent, as one text packet per changed row covering its first through
+    /// last changed character (see [`Screen::update_packet_count`]). Content
+    /// that is not in `screen` is overwritten with its spaces. This avoids
+    /// the blank flash of [`Device::clear_screen`] followed by several
+    /// [`Device::set_text`] calls.
+    ///
+    /// Every row is sent if the display's contents are not fully known, i.e.
+    /// on the first redraw after connecting or after
+    /// [`Device::invalidate_cache`], unless the screen has been cleared or
+    /// read back with [`Device::read_screen`] in the meantime.
+    pub fn redraw(&mut self, screen: &Screen) -> Result<(), Error> {
+        for row in 0..NUM_ROWS {
+            let span = if self.shadow_known {
+                screen.changed_span(&self.shadow, row)
+            } else {
+                Some((0, NUM_COLUMNS - 1))
+            };
+            if let Some((first, last)) = span {
+                let text = &screen.rows[usize::from(row)][usize::from(first)..=usize::from(last)];
+                self.set_text(row, first, text)?;
+            }
+        }
+        // Not reached if a row failed, so an unknown screen is resent in full.
+        self.shadow_known = true;
+        Ok(())
+    }
+
+    /// Forgets all host-side knowledge of the device's state.
+    ///
+    /// Afterwards, cached settings like [`Device::cursor_position`] are
+    /// unknown, [`Device::screen_snapshot`] is blank, and the next
+    /// [`Device::apply_frame`] and [`Device::flush_framebuffer`] send
+    /// everything instead of only the parts that changed.
+    ///
+    /// Call this when the display may have been changed by something other
+    /// than this `Device`, for example after reconnecting, after the device
+    /// restored its boot state, or when another program has written to it.
+    pub fn invalidate_cache(&mut self) {
+        self.cache = StateCache::default();
+        self.last_frame = Frame::new();
+        self.shadow = Screen::blank();
+        self.shadow_known = false;
+        self.key_reporting = None;
+        self.held_keys = 0;
+    }
+
+    /// Runs a test pattern that exercises the display, for checking a newly
+    /// connected device.
+    ///
+    /// The pattern consists of the following steps, with a short pause after
+    /// each one:
+    ///
+    /// 1. Each row is filled in turn, to check that every character cell
+    ///    works and that rows are addressed correctly.
+    ///
+    /// 2. Each LED is lit red, then green, then turned off, to check the LED
+    ///    wiring and both color components.
+    ///
+    /// 3. The contrast is ramped from light to dark.
+    ///
+    /// 4. The screen and keypad backlights are ramped from off to full
+    ///    brightness.
+    ///
+    /// 5. All 256 character codes are drawn, one screen at a time, to show
+    ///    the full character generator ROM (codes `0..8` are the custom
+    ///    characters).
+    ///
+    /// Afterwards, the screen is cleared, the LEDs are left off, and the
+    /// contrast and backlight are restored to their previous values (read
+    /// from the device if not known). The boot state is not saved, so power
+    /// cycling the device also undoes the test pattern. This call blocks for
+    /// several seconds.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidRead` - If the previous contrast and backlight are not known
+    ///   and cannot be read from the device (see [`Device::read_status`]).
+    ///   Nothing is changed on the display in that case.
+    pub fn test_pattern(&mut self) -> Result<(), Error> {
+        const STEP_DELAY: Duration = Duration::from_millis(500);
+        const RAMP_DELAY: Duration = Duration::from_millis(50);
+
+        let cached = (self.cache.contrast, self.cache.screen_backlight);
+        let (contrast, screen_backlight) = match cached {
+            (Some(contrast), Some(screen_backlight)) => (contrast, screen_backlight),
+            _ => {
+                let status = self.read_status()?;
+                (status.contrast, status.backlight)
+            }
+        };
+        let keypad_backlight = self.cache.keypad_backlight.unwrap_or(screen_backlight);
+
+        // 1. Rows
+        for row in 0..NUM_ROWS {
+            self.clear_screen()?;
+            self.set_text(row, 0, &[b'#'; NUM_COLUMNS as usize])?;
+            self.clock.sleep(STEP_DELAY);
+        }
+        self.clear_screen()?;
+
+        // 2. LEDs
+        for index in 0..NUM_LEDS {
+            self.set_led(index, 100, 0)?;
+            self.clock.sleep(STEP_DELAY);
+            self.set_led(index, 0, 100)?;
+            self.clock.sleep(STEP_DELAY);
+            self.set_led(index, 0, 0)?;
+        }
+
+        // 3. Contrast
+        self.fill_rows(b"Contrast")?;
+        for contrast in (60..=150).step_by(5) {
+            self.set_contrast(contrast)?;
+            self.clock.sleep(RAMP_DELAY);
+        }
+        self.set_contrast(contrast)?;
+        self.clock.sleep(STEP_DELAY);
+
+        // 4. Backlight
+        self.fill_rows(b"Backlight")?;
+        for brightness in (0..=100).step_by(5) {
+            self.set_backlight(brightness, brightness)?;
+            self.clock.sleep(RAMP_DELAY);
+        }
+        self.clock.sleep(STEP_DELAY);
+
+        // 5. Characters
+        let codes: Vec<u8> = (0..=255).collect();
+        for screen in codes.chunks(usize::from(NUM_ROWS) * usize::from(NUM_COLUMNS)) {
+            self.clear_screen()?;
+            for (row, text) in (0..).zip(screen.chunks(NUM_COLUMNS.into())) {
+                self.set_text(row, 0, text)?;
+            }
+            self.clock.sleep(STEP_DELAY * 4);
+        }
+
+        self.clear_screen()?;
+        self.set_backlight(screen_backlight, keypad_backlight)?;
+        Ok(())
+    }
+
+    /// Applies every setting in `config` to the device.
+    ///
+    /// All settings are validated before anything is sent, so an invalid
+    /// config leaves the device unchanged. Settings that are `None` are left
+    /// as they are. If [`DeviceConfig::save_boot_state`] is set, the result is
+    /// saved with [`Device::save_boot_state`] at the end.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the cursor position is out of bounds (as
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]), or if a custom
+    ///   character index is out of bounds (as defined by
+    ///   [`limits::CGRAM_SLOTS`]). The error's `field` is `"row"`, `"col"` or
+    ///   `"index"` respectively.
+    pub fn apply_config(&mut self, config: &DeviceConfig) -> Result<(), Error> {
+        if let Some((row, col)) = config.cursor_position {
+            check_range("row", row, NUM_ROWS)?;
+            check_range("col", col, NUM_COLUMNS)?;
+        }
+        for &(index, _) in config.custom_chars.iter().flatten() {
+            check_range("index", index, limits::CGRAM_SLOTS)?;
+        }
+
+        if config.contrast.is_some() {
+            self.set_contrast(config.contrast)?;
+        }
+        if config.backlight.is_some() || config.keypad_backlight.is_some() {
+            self.set_backlight(config.backlight, config.keypad_backlight)?;
+        }
+        if let Some(style) = config.cursor_style {
+            self.set_cursor_style(style)?;
+        }
+        if let Some((row, col)) = config.cursor_position {
+            self.set_cursor_position(row, col)?;
+        }
+        if let Some(reporting) = &config.key_reporting {
+            self.configure_key_reporting(&reporting.press, &reporting.release)?;
+        }
+        for &(index, bitmap) in config.custom_chars.iter().flatten() {
+            self.set_custom_character(index, bitmap)?;
+        }
+        if config.save_boot_state {
+            self.save_boot_state()?;
+        }
+        Ok(())
+    }
+
+    /// Compares the current state of the device with `expected`, returning
+    /// the differences.
+    ///
+    /// An empty result means that everything matches. Only the parts of
+    /// `expected` that are specified are read and compared. The cursor is not
+    /// checked, because it cannot be read back from the device.
+    ///
+    /// This reads the live state, not the saved boot state directly. To check
+    /// that [`Device::save_boot_state`] persisted correctly, power cycle or
+    /// reboot the device first, so the boot state is loaded again. This
+    /// includes custom characters, which are read back one slot at a time
+    /// with [`Device::read_custom_character`].
+    pub fn verify_boot_state(
+        &mut self,
+        expected: &BootState,
+    ) -> Result<Vec<BootStateMismatch>, Error> {
+        let mut mismatches = Vec::new();
+        if let Some(expected) = &expected.screen {
+            let actual = self.read_screen()?;
+            if actual != *expected {
+                mismatches.push(BootStateMismatch::Screen {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        for (index, expected) in (0..).zip(&expected.custom_characters) {
+            let Some(expected) = expected else {
+                continue;
+            };
+            let expected = expected.map(|row| row & GLYPH_PIXEL_MASK);
+            let actual = self.read_custom_character(index)?;
+            if actual != expected {
+                mismatches.push(BootStateMismatch::CustomCharacter {
+                    index,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        let needs_status = expected.contrast.is_some()
+            || expected.backlight.is_some()
+            || expected.keypad_backlight.is_some()
+            || expected.key_reporting.is_some();
+        if !needs_status {
+            return Ok(mismatches);
+        }
+        let status = self.read_status()?;
+        if let Some(expected) = expected.contrast.filter(|&c| c != status.contrast) {
+            mismatches.push(BootStateMismatch::Contrast {
+                expected,
+                actual: status.contrast,
+            });
+        }
+        if let Some(expected) = expected.backlight.filter(|&b| b != status.backlight) {
+            mismatches.push(BootStateMismatch::Backlight {
+                expected,
+                actual: status.backlight,
+            });
+        }
+        if let Some(expected) = expected
+            .keypad_backlight
+            .filter(|&b| Some(b) != status.keypad_backlight)
+        {
+            mismatches.push(BootStateMismatch::KeypadBacklight {
+                expected,
+                actual: status.keypad_backlight,
+            });
+        }
+        if let Some(expected) = &expected.key_reporting {
+            if expected.masks() != (status.key_press_mask, status.key_release_mask) {
+                mismatches.push(BootStateMismatch::KeyReporting {
+                    expected: expected.clone(),
+                    actual: KeyReporting::from_masks(
+                        status.key_press_mask,
+                        status.key_release_mask,
+                    ),
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// Sets the output level of a GPIO pin (command `0x22`).
+    ///
+    /// `value` is 0 for low, 100 for high, or in between for a PWM duty cycle
+    /// in percent. A value higher than 100 is interpreted as high. The pin's
+    /// drive mode is left unchanged.
+    ///
+    /// Pins 5 to 12 drive the indicator LEDs (see [`Device::led_gpio_pins`]);
+    /// [`Device::set_led`] is a convenience wrapper for those.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the pin index is out of bounds (as defined by
+    ///   [`NUM_GPIO_PINS`]). The error's `field` is `"index"`.
+    pub fn set_gpio(&mut self, index: u8, value: u8) -> Result<(), Error> {
+        check_range("index", index, NUM_GPIO_PINS)?;
+        self.transact(&Packet::new(0x22, &[index, value]))?;
+        Ok(())
+    }
+
+    /// Reads the state and configuration of a GPIO pin.
+    ///
+    /// Reading a pin resets its edge flags ([`GpioState::fell`] and
+    /// [`GpioState::rose`]).
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the pin index is out of bounds (as defined by
+    ///   [`NUM_GPIO_PINS`]). The error's `field` is `"index"`.
+    pub fn read_gpio(&mut self, index: u8) -> Result<GpioState, Error> {
+        check_range("index", index, NUM_GPIO_PINS)?;
+        let response = self.transact(&Packet::new(0x23, &[index]))?;
+        match *response.data() {
+            [echo, state, requested_level, function] if echo == index => Ok(GpioState {
+                index,
+                level: state & 0x01 != 0,
+                fell: state & 0x02 != 0,
+                rose: state & 0x04 != 0,
+                requested_level,
+                function,
+            }),
+            _ => Err(Error::InvalidRead),
+        }
+    }
+
+    /// Reads the state and configuration of every GPIO pin, in order of their
+    /// index (`0..NUM_GPIO_PINS`).
+    ///
+    /// This takes one command per pin ([`NUM_GPIO_PINS`] in total), so it is
+    /// much slower than reading a single pin. Like [`Device::read_gpio`], it
+    /// resets the edge flags of every pin.
+    pub fn read_all_gpio(&mut self) -> Result<Vec<GpioState>, Error> {
+        (0..NUM_GPIO_PINS)
+            .map(|index| self.read_gpio(index))
+            .collect()
+    }
+
+    /// Sets up a live display slot (command `0x15`), which makes the device
+    /// show a sensor reading on the screen and keep it up to date by itself.
+    ///
+    /// The reading is drawn at the given position and takes up
+    /// [`LiveDisplayItem::width`] characters. Sensors are connected through a
+    /// fan/temperature expansion board; slots showing sensors that are not
+    /// connected are left blank. Use [`LiveDisplayItem::Disabled`] to stop
+    /// updating a slot (its last reading stays on the screen).
+    ///
+    /// The slot configuration is part of the boot state (see
+    /// [`Device::save_boot_state`]).
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If an index is out of bounds. The error's
+    ///   `field` is `"slot"` (see [`limits::LIVE_DISPLAY_SLOTS`]), `"row"`,
+    ///   `"col"`, `"sensor"` (see [`limits::TEMPERATURE_SENSORS`]), `"fan"`
+    ///   (see [`limits::FANS`]) or `"pulses_per_revolution"` (1 to 8).
+    pub fn configure_live_display(
+        &mut self,
+        slot: u8,
+        item: LiveDisplayItem,
+        row: u8,
+        col: u8,
+    ) -> Result<(), Error> {
+        check_range("slot", slot, limits::LIVE_DISPLAY_SLOTS)?;
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        let (kind, index, digits, extra) = match item {
+            LiveDisplayItem::Disabled => (0, 0, 0, 0),
+            LiveDisplayItem::Fan {
+                fan,
+                pulses_per_revolution,
+            } => {
+                check_range("fan", fan, limits::FANS)?;
+                check_bounds("pulses_per_revolution", pulses_per_revolution.into(), 1, 8)?;
+                (1, fan, item.width(), pulses_per_revolution)
+            }
+            LiveDisplayItem::Temperature { sensor, unit, .. } => {
+                check_range("sensor", sensor, limits::TEMPERATURE_SENSORS)?;
+                (2, sensor, item.width() - 1, unit as u8)
+            }
+        };
+        let data = [slot, kind, index, digits, col, row, extra];
+        self.transact(&Packet::new(0x15, &data))?;
+        Ok(())
+    }
+
+    /// Configures the ATX power switch functions (command `0x1C`).
+    ///
+    /// With the ATX functions enabled, the device can act as the front panel
+    /// of a PC: it drives the motherboard's power and reset switch lines
+    /// from its GPIO pins, and senses whether the host is powered. See
+    /// [`AtxConfig`] for the available functions, and the datasheet for the
+    /// wiring.
+    ///
+    /// The ATX configuration is part of the boot state (see
+    /// [`Device::save_boot_state`]).
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If [`AtxConfig::power_pulse`] is zero. The
+    ///   error's `field` is `"power_pulse"`.
+    pub fn configure_atx(&mut self, config: AtxConfig) -> Result<(), Error> {
+        check_bounds("power_pulse", config.power_pulse.into(), 1, u8::MAX.into())?;
+        self.transact(&Packet::new(0x1c, &[config.flags(), config.power_pulse]))?;
+        Ok(())
+    }
+
+    /// Arms the host watchdog (command `0x1D`), or disarms it if
+    /// `timeout_secs` is zero.
+    ///
+    /// Once armed, the device resets the host through the ATX reset line
+    /// (see [`Device::configure_atx`]) if this command is not sent again
+    /// within `timeout_secs` seconds. Other commands do not count: the host
+    /// must keep calling `set_watchdog` more often than the timeout, or the
+    /// machine is reset. Disarm the watchdog before shutting down the program
+    /// that feeds it.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), cfa635::Error> {
+    /// use cfa635::Device;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let mut device = Device::new("/dev/ttyACM0")?;
+    /// loop {
+    ///     // Reset the host if this loop stalls for 30 seconds.
+    ///     device.set_watchdog(30)?;
+    ///     thread::sleep(Duration::from_secs(10));
+    /// }
+    /// # }
+    /// ```
+    pub fn set_watchdog(&mut self, timeout_secs: u8) -> Result<(), Error> {
+        self.transact(&Packet::new(0x1d, &[timeout_secs]))?;
+        Ok(())
+    }
+
+    /// Reads the current reporting configuration and status of the device.
+    ///
+    /// See [`DeviceStatus`] for the meaning of each field.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidRead` - If the response is too short to be decoded.
+    pub fn read_status(&mut self) -> Result<DeviceStatus, Error> {
+        let response = self.transact(&Packet::new(0x1e, &[]))?;
+        let status = DeviceStatus::from_data(response.data()).ok_or(Error::InvalidRead)?;
+        self.cache.contrast = Some(status.contrast);
+        self.cache.screen_backlight = Some(status.backlight);
+        if status.keypad_backlight.is_some() {
+            self.cache.keypad_backlight = status.keypad_backlight;
+        }
+        Ok(status)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum CursorStyle {
+    NoCursor = 0,
+    BlinkingBlock = 1,
+    StaticUnderscore = 2,
+    BlinkingUnderscore = 3,
+}
+
+/// A handle for interrupting blocking calls on a [`Device`] from another
+/// thread, e.g. for a clean shutdown.
+///
+/// After [`InterruptHandle::interrupt`] is called, the next blocking wait on
+/// the device returns [`Error::Interrupted`]. Waits that are affected include
+/// waiting for a command's response and waiting for a report (e.g.
+/// [`Device::wait_report`]).
+///
+/// Interrupts are checked between reads from the serial port, so an
+/// interrupted call may still block until the current read times out. The
+/// port's read timeout ([`DEFAULT_READ_TIMEOUT`] unless changed with
+/// [`DeviceBuilder::timeout`]) is therefore the upper bound on how long an
+/// interrupt takes to be noticed; use a short timeout if this needs to be
+/// responsive.
+///
+/// The interrupt request is cleared once it has been observed, so the device
+/// can be used again afterwards.
+#[derive(Debug, Clone)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Requests that the current or next blocking call on the device return
+    /// [`Error::Interrupted`].
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+}
+
+/// The color of an indicator LED, as a combination of its red and green
+/// components.
+///
+/// The brightness of each component is a value between 0 (off) and 100 (max
+/// brightness).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedColor {
+    pub red: u8,
+    pub green: u8,
+}
+
+impl LedColor {
+    /// Both components off.
+    pub const OFF: Self = Self::new(0, 0);
+
+    /// Full-brightness red.
+    pub const RED: Self = Self::new(100, 0);
+
+    /// Full-brightness green.
+    pub const GREEN: Self = Self::new(0, 100);
+
+    /// Red and green at full brightness, which appears yellow.
+    pub const YELLOW: Self = Self::new(100, 100);
+
+    /// Full red with half green, which appears orange.
+    pub const ORANGE: Self = Self::new(100, 50);
+
+    pub const fn new(red: u8, green: u8) -> Self {
+        Self { red, green }
+    }
+
+    /// Like [`LedColor::new`], but limits each component to the valid range
+    /// (at most 100).
+    pub fn clamped(red: u8, green: u8) -> Self {
+        Self::new(red.min(100), green.min(100))
+    }
+}
+
+/// The hardware and firmware versions of a device, as returned by
+/// [`Device::version`].
+///
+/// The device reports its versions as a string of the form
+/// `CFA635:hX.Y,fW.Z`, which is split into its three parts. The `h` and `f`
+/// prefixes are removed, so for `CFA635:h1.5,f1.0` the hardware version is
+/// `1.5` and the firmware version is `1.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Version {
+    /// The model name, e.g. `CFA635`.
+    pub model: String,
+
+    /// The hardware revision, e.g. `1.5`.
+    pub hardware: String,
+
+    /// The firmware revision, e.g. `1.0`.
+    pub firmware: String,
+}
+
+impl Version {
+    /// Parses the data payload of a version response, e.g. as returned by
+    /// [`Device::raw_transact`] for command 0x01.
+    ///
+    /// Returns `None` if the payload is not of the form described above.
+    /// Trailing padding (NUL bytes and spaces) is ignored.
+    pub fn from_data(data: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(data)
+            .ok()?
+            .trim_end_matches(['\0', ' ']);
+        let (model, versions) = text.split_once(':')?;
+        let (hardware, firmware) = versions.split_once(',')?;
+        let hardware = hardware.strip_prefix('h')?;
+        let firmware = firmware.strip_prefix('f')?;
+        if model.is_empty() || hardware.is_empty() || firmware.is_empty() {
+            return None;
+        }
+        Some(Self {
+            model: model.to_owned(),
+            hardware: hardware.to_owned(),
+            firmware: firmware.to_owned(),
+        })
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:h{},f{}", self.model, self.hardware, self.firmware)
+    }
+}
+
+/// Round-trip time statistics, as returned by [`Device::measure_latency`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct LatencyStats {
+    /// The number of round trips measured.
+    pub samples: usize,
+
+    /// The fastest round trip.
+    pub min: Duration,
+
+    /// The slowest round trip.
+    pub max: Duration,
+
+    /// The average round trip.
+    pub mean: Duration,
+
+    /// The standard deviation of the round trip times.
+    pub stddev: Duration,
+}
+
+impl LatencyStats {
+    /// Calculates the statistics of a non-empty set of samples.
+    fn from_samples(times: &[Duration]) -> Self {
+        let count = times.len() as u32;
+        let mean = times.iter().sum::<Duration>() / count;
+        let variance = times
+            .iter()
+            .map(|&time| (time.as_secs_f64() - mean.as_secs_f64()).powi(2))
+            .sum::<f64>()
+            / f64::from(count);
+        Self {
+            samples: times.len(),
+            min: times.iter().copied().min().unwrap_or_default(),
+            max: times.iter().copied().max().unwrap_or_default(),
+            mean,
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        }
+    }
+}
+
+/// The state of a GPIO pin, as returned by [`Device::read_gpio`] (command
+/// `0x23`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GpioState {
+    /// The index of the pin.
+    pub index: u8,
+
+    /// The level of the pin when it was last sampled (`true` is high).
+    pub level: bool,
+
+    /// Whether at least one falling edge was detected since the pin was last
+    /// read.
+    pub fell: bool,
+
+    /// Whether at least one rising edge was detected since the pin was last
+    /// read.
+    pub rose: bool,
+
+    /// The output level or PWM duty cycle (0-100) that was last requested for
+    /// the pin.
+    pub requested_level: u8,
+
+    /// The raw function setting of the pin.
+    ///
+    /// Bits 0-2 are the drive mode, and bit 3 is set if the pin is used as a
+    /// general-purpose I/O (as opposed to being reserved by the device).
+    pub function: u8,
+}
+
+/// How [`Device::tail`] shows lines that are wider than the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOverflow {
+    /// Cut the line off at the edge of the screen.
+    Truncate,
+    /// Continue the line on the following rows.
+    Wrap,
+}
+
+/// A character position on the screen that is known to be in bounds.
+///
+/// Methods that take a separate row and column (like [`Device::set_text`])
+/// check them on every call, and are easy to call with the two swapped. A
+/// `Position` is checked once when it is created, and names both fields, for
+/// use with [`Device::set_text_at`] and [`Device::set_cursor_position_at`].
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::{Device, Position};
+///
+/// let mut device = Device::new("/dev/ttyACM0")?;
+/// let status = Position::new(3, 12)?;
+/// device.set_text_at(status, b"[ OK ]")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    row: u8,
+    col: u8,
+}
+
+impl Position {
+    /// The top-left corner of the screen.
+    pub const ORIGIN: Position = Position { row: 0, col: 0 };
+
+    /// Creates a position from a row and column index.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the row or column index is out of bounds (as
+    ///   defined by [`NUM_ROWS`] and [`NUM_COLUMNS`]). The error's `field` is
+    ///   `"row"` or `"col"` respectively.
+    pub fn new(row: u8, col: u8) -> Result<Self, Error> {
+        check_range("row", row, NUM_ROWS)?;
+        check_range("col", col, NUM_COLUMNS)?;
+        Ok(Self { row, col })
+    }
+
+    /// Returns the row index.
+    pub const fn row(self) -> u8 {
+        self.row
+    }
+
+    /// Returns the column index.
+    pub const fn col(self) -> u8 {
+        self.col
+    }
+}
+
+impl From<Position> for (u8, u8) {
+    /// Returns the position as `(row, col)`, the order used by
+    /// [`Device::cursor_position`].
+    fn from(position: Position) -> Self {
+        (position.row, position.col)
+    }
+}
+
+/// One of the indicator LEDs, with an index that is known to be in bounds.
+///
+/// Numbered starting with zero (0) at the top, like the `u8` indices accepted
+/// by the LED methods (see [`IntoLed`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Led(u8);
+
+impl Led {
+    /// All of the LEDs, from top to bottom.
+    pub const ALL: [Led; NUM_LEDS as usize] = [Led(0), Led(1), Led(2), Led(3)];
+
+    /// Returns the LED with the given index, or `None` if the index is out of
+    /// bounds (as defined by [`NUM_LEDS`]).
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < NUM_LEDS {
+            Some(Self(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the index of the LED.
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+}
+
+impl From<Led> for u8 {
+    fn from(led: Led) -> Self {
+        led.0
+    }
+}
+
+/// Types that can select an indicator LED: a [`Led`], or a raw `u8` index
+/// that is checked when it is used.
+pub trait IntoLed {
+    /// Converts to a [`Led`].
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the index is out of bounds (as defined by
+    ///   [`NUM_LEDS`]). The error's `field` is `"index"`.
+    fn into_led(self) -> Result<Led, Error>;
+}
+
+impl IntoLed for Led {
+    fn into_led(self) -> Result<Led, Error> {
+        Ok(self)
+    }
+}
+
+impl IntoLed for u8 {
+    fn into_led(self) -> Result<Led, Error> {
+        check_range("index", self, NUM_LEDS)?;
+        Ok(Led(self))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Report {
+    KeyActivity { key: Key, pressed: bool },
+}
+
+impl Report {
+    pub fn from_raw(packet: &Packet) -> Option<Self> {
+        match packet.packet_type() {
+            0x80 => {
+                let data = match packet.data().first() {
+                    Some(&x) => x,
+                    None => {
+                        log::warn!("not enough bytes for a key activity report");
+                        return None;
+                    }
+                };
+                let (key, pressed) = match Key::from_code(data) {
+                    Some(event) => event,
+                    None => {
+                        log::warn!("unknown key code {:?}", data);
+                        return None;
+                    }
+                };
+                Some(Self::KeyActivity { key, pressed })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What to do with a report that arrives when the report buffer is full (see
+/// [`Device::set_report_buffer_limit`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportOverflow {
+    /// Drop the oldest buffered report to make room for the new one.
+    #[default]
+    DropOldest,
+
+    /// Drop the new report, keeping the buffered ones.
+    DropNewest,
+}
+
+/// Formats the report for display, e.g. `Up pressed` or `Enter released`.
+///
+/// This format is considered stable.
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::KeyActivity { key, pressed } => {
+                let action = if *pressed { "pressed" } else { "released" };
+                write!(f, "{} {}", key, action)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Exit,
+}
+
+impl Key {
+    const ALL: [Key; 6] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::Enter,
+        Self::Exit,
+    ];
+
+    /// Decodes the key code of a key activity report, returning the key and
+    /// whether it was pressed (`true`) or released (`false`).
+    ///
+    /// Returns `None` for unknown codes. This is the inverse of
+    /// [`Key::press_code`] and [`Key::release_code`].
+    pub fn from_code(code: u8) -> Option<(Key, bool)> {
+        match code {
+            1..=6 => Some((Self::ALL[usize::from(code - 1)], true)),
+            7..=12 => Some((Self::ALL[usize::from(code - 7)], false)),
+            _ => None,
+        }
+    }
+
+    /// Returns the code of a key activity report for this key being pressed.
+    pub fn press_code(self) -> u8 {
+        match self {
+            Self::Up => 1,
+            Self::Down => 2,
+            Self::Left => 3,
+            Self::Right => 4,
+            Self::Enter => 5,
+            Self::Exit => 6,
+        }
+    }
+
+    /// Returns the code of a key activity report for this key being released.
+    pub fn release_code(self) -> u8 {
+        self.press_code() + 6
+    }
+
+    /// Returns the keys whose bits are set in the given key mask.
+    fn from_mask(mask: u8) -> Vec<Key> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(|key| mask & key.mask() != 0)
+            .collect()
+    }
+
+    fn mask(&self) -> u8 {
+        match self {
+            Self::Up => 0x01,
+            Self::Enter => 0x02,
+            Self::Exit => 0x04,
+            Self::Left => 0x08,
+            Self::Right => 0x10,
+            Self::Down => 0x20,
+        }
+    }
+}
+
+/// Formats the key's name for display, e.g. `Up` or `Enter`.
+///
+/// This format is considered stable.
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Self::Up => "Up",
+            Self::Down => "Down",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Enter => "Enter",
+            Self::Exit => "Exit",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Which key events are reported by the device, as returned by
+/// [`Device::key_reporting_config`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyReporting {
+    /// Keys that have press events reported.
+    pub press: Vec<Key>,
+
+    /// Keys that have release events reported.
+    pub release: Vec<Key>,
+}
+
+impl KeyReporting {
+    fn from_masks(press_mask: u8, release_mask: u8) -> Self {
+        Self {
+            press: Key::from_mask(press_mask),
+            release: Key::from_mask(release_mask),
+        }
+    }
+
+    /// Returns the press and release masks, as used by the device.
+    fn masks(&self) -> (u8, u8) {
+        let mask = |keys: &[Key]| keys.iter().map(Key::mask).fold(0, |a, b| a | b);
+        (mask(&self.press), mask(&self.release))
+    }
+}
+
+/// The state of the keypad, as returned by [`Device::read_keypad`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct KeypadState {
+    /// Keys that are currently held down.
+    pub currently_pressed: Vec<Key>,
+
+    /// Keys that were pressed at least once since the keypad was last read.
+    pub presses_since_last_poll: Vec<Key>,
+
+    /// Keys that were released at least once since the keypad was last read.
+    pub releases_since_last_poll: Vec<Key>,
+}
+
+/// A sensor reading shown by a live display slot, as configured with
+/// [`Device::configure_live_display`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveDisplayItem {
+    /// The slot is not updated.
+    Disabled,
+
+    /// The speed of a fan (`0..4`), in RPM.
+    Fan {
+        fan: u8,
+        /// The number of tachometer pulses the fan produces per revolution
+        /// (1 to 8), used to convert the pulse rate to RPM. Most PC fans
+        /// produce 2.
+        pulses_per_revolution: u8,
+    },
+
+    /// The reading of a temperature sensor (`0..32`).
+    Temperature {
+        sensor: u8,
+        unit: TemperatureUnit,
+        /// Whether to show tenths of a degree, e.g. `23.4` instead of `23`.
+        tenths: bool,
+    },
+}
+
+impl LiveDisplayItem {
+    /// Returns the number of characters the item takes up on the screen.
+    ///
+    /// Temperatures include their unit (`C` or `F`).
+    pub fn width(&self) -> u8 {
+        match self {
+            Self::Disabled => 0,
+            Self::Fan { .. } => 4,
+            Self::Temperature { tenths: false, .. } => 4,
+            Self::Temperature { tenths: true, .. } => 6,
+        }
+    }
+}
+
+/// The ATX power switch functions, as configured with
+/// [`Device::configure_atx`].
+///
+/// The default disables every function.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::{AtxConfig, Device};
+///
+/// let mut device = Device::new("/dev/ttyACM0")?;
+/// device.configure_atx(AtxConfig {
+///     auto_polarity: true,
+///     keypad_power_on: true,
+///     keypad_power_off: true,
+///     ..AtxConfig::default()
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtxConfig {
+    /// Detect the polarity of the reset and power lines automatically
+    /// (recommended). Overrides `reset_invert` and `power_invert`.
+    pub auto_polarity: bool,
+
+    /// Drive the reset line high instead of low.
+    pub reset_invert: bool,
+
+    /// Drive the power line high instead of low.
+    pub power_invert: bool,
+
+    /// Turn off the screen and backlights while the host is powered off.
+    pub lcd_off_if_host_off: bool,
+
+    /// Reset the host when the Enter key is held for four seconds while the
+    /// host is on.
+    pub keypad_reset: bool,
+
+    /// Power on the host when the Enter key is pressed while the host is off.
+    pub keypad_power_on: bool,
+
+    /// Power off the host when the Exit key is held for four seconds while
+    /// the host is on.
+    pub keypad_power_off: bool,
+
+    /// The length of the pulse on the power line, in 1/32 seconds (1 to
+    /// 255). The default is 32 (one second).
+    pub power_pulse: u8,
+}
+
+impl AtxConfig {
+    const AUTO_POLARITY: u8 = 0x01;
+    const RESET_INVERT: u8 = 0x02;
+    const POWER_INVERT: u8 = 0x04;
+    const LCD_OFF_IF_HOST_IS_OFF: u8 = 0x10;
+    const KEYPAD_RESET: u8 = 0x20;
+    const KEYPAD_POWER_ON: u8 = 0x40;
+    const KEYPAD_POWER_OFF: u8 = 0x80;
+
+    /// Decodes a raw flags byte, e.g. [`DeviceStatus::atx_flags`].
+    ///
+    /// The flags do not include the power pulse length, so it is set to the
+    /// default.
+    pub fn from_flags(flags: u8) -> Self {
+        let has = |flag: u8| flags & flag != 0;
+        Self {
+            auto_polarity: has(Self::AUTO_POLARITY),
+            reset_invert: has(Self::RESET_INVERT),
+            power_invert: has(Self::POWER_INVERT),
+            lcd_off_if_host_off: has(Self::LCD_OFF_IF_HOST_IS_OFF),
+            keypad_reset: has(Self::KEYPAD_RESET),
+            keypad_power_on: has(Self::KEYPAD_POWER_ON),
+            keypad_power_off: has(Self::KEYPAD_POWER_OFF),
+            ..Self::default()
+        }
+    }
+
+    /// Encodes the enabled functions as a raw flags byte.
+    pub fn flags(&self) -> u8 {
+        [
+            (self.auto_polarity, Self::AUTO_POLARITY),
+            (self.reset_invert, Self::RESET_INVERT),
+            (self.power_invert, Self::POWER_INVERT),
+            (self.lcd_off_if_host_off, Self::LCD_OFF_IF_HOST_IS_OFF),
+            (self.keypad_reset, Self::KEYPAD_RESET),
+            (self.keypad_power_on, Self::KEYPAD_POWER_ON),
+            (self.keypad_power_off, Self::KEYPAD_POWER_OFF),
+        ]
+        .into_iter()
+        .filter(|&(enabled, _)| enabled)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+}
+
+impl Default for AtxConfig {
+    fn default() -> Self {
+        Self {
+            auto_polarity: false,
+            reset_invert: false,
+            power_invert: false,
+            lcd_off_if_host_off: false,
+            keypad_reset: false,
+            keypad_power_on: false,
+            keypad_power_off: false,
+            power_pulse: 32,
+        }
+    }
+}
+
+/// The unit of a temperature reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    Celsius = 0,
+    Fahrenheit = 1,
+}
+
+/// The reporting configuration and status of the device, as returned by
+/// [`Device::read_status`] (command `0x1E`).
+///
+/// Note: The CFA635 does not report any error flags or the reason for its last
+/// reset. The closest indicator is [`DeviceStatus::watchdog_counter`]; if it is
+/// non-zero, the host watchdog is armed and the device will reset the host if
+/// it stops receiving commands.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct DeviceStatus {
+    /// Bitmask of the fans (1-4) that have reporting enabled.
+    ///
+    /// Only relevant if a fan/temperature expansion board is connected.
+    pub fan_reporting: u8,
+
+    /// Bitmask of the temperature sensors (1-32) that have reporting enabled.
+    ///
+    /// Bit 0 corresponds to sensor 1, bit 31 to sensor 32.
+    pub temperature_reporting: u32,
+
+    /// Raw mask of the keys that have press reporting enabled (see
+    /// [`Device::configure_key_reporting`]).
+    pub key_press_mask: u8,
+
+    /// Raw mask of the keys that have release reporting enabled (see
+    /// [`Device::configure_key_reporting`]).
+    pub key_release_mask: u8,
+
+    /// Raw ATX power switch configuration flags (command `0x1C`). See
+    /// [`DeviceStatus::atx_config`] for the decoded flags.
+    pub atx_flags: u8,
+
+    /// Host watchdog timeout in seconds (see [`Device::set_watchdog`]), or
+    /// zero if the watchdog is disabled.
+    pub watchdog_counter: u8,
+
+    /// RPM glitch filter delay for each of the four fans (command `0x11`).
+    pub fan_glitch_delay: [u8; 4],
+
+    /// Current LCD contrast (see [`Device::set_contrast`]).
+    pub contrast: u8,
+
+    /// Current screen backlight brightness (see [`Device::set_backlight`]).
+    pub backlight: u8,
+
+    /// Current keypad backlight brightness (see [`Device::set_backlight`]).
+    ///
+    /// Older firmware does not report this value.
+    pub keypad_backlight: Option<u8>,
+}
+
+impl DeviceStatus {
+    /// The minimum length of a valid status response.
+    const MIN_LEN: usize = 15;
+
+    /// Decodes the data payload of a status response.
+    ///
+    /// Returns `None` if the payload is too short.
+    pub fn from_data(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::MIN_LEN {
+            log::warn!("not enough bytes for a status response");
+            return None;
+        }
+        Some(Self {
+            fan_reporting: data[0],
+            temperature_reporting: u32::from_le_bytes([data[1], data[2], data[3], data[4]]),
+            key_press_mask: data[5],
+            key_release_mask: data[6],
+            atx_flags: data[7],
+            watchdog_counter: data[8],
+            fan_glitch_delay: [data[9], data[10], data[11], data[12]],
+            contrast: data[13],
+            backlight: data[14],
+            keypad_backlight: data.get(15).copied(),
+        })
+    }
+
+    /// Returns the decoded ATX power switch configuration (see
+    /// [`Device::configure_atx`]).
+    ///
+    /// The status does not include the power pulse length, so it is set to
+    /// the default.
+    pub fn atx_config(&self) -> AtxConfig {
+        AtxConfig::from_flags(self.atx_flags)
+    }
+
+    /// Returns `true` if the host watchdog is armed.
+    pub fn watchdog_armed(&self) -> bool {
+        self.watchdog_counter != 0
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("serialport: {0}")]
+    SerialPort(#[from] serialport::Error),
+
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Read an unexpected/incorrect byte, either because of an incompatibility
+    /// or desync.
+    #[error("invalid read - potential desync on serial port")]
+    InvalidRead,
+
+    /// An argument to a function call had a value that was out of range.
+    ///
+    /// `field` is the name of the offending argument, `value` is the value
+    /// that was passed, and `min` and `max` are the smallest and largest
+    /// allowed values. See the individual functions' documentation for more
+    /// details about allowed values of their arguments.
+    #[error("invalid value for argument `{field}`: {value} (expected {min} to {max})")]
+    #[non_exhaustive]
+    InvalidArgument {
+        field: &'static str,
+        value: usize,
+        min: usize,
+        max: usize,
+    },
+
+    /// The device returned an error in its response to our command.
+    ///
+    /// The cause is usually an error that is out of our control, e.g.
+    /// [`Device::save_boot_state`] may return this error if the device doesn't
+    /// read back the correct data after saving, which is unlikely but may
+    /// eventually happen because of a worn-out flash.
+    ///
+    /// `command` is the code of the rejected command, and `data` is the
+    /// payload of the error response, which is usually empty but may carry
+    /// diagnostic bytes on some firmware versions.
+    #[error("Device returned an error response to command {command:#04x}")]
+    #[non_exhaustive]
+    ReturnedError { command: u8, data: Vec<u8> },
+
+    /// The device's responses have the expected contents but fail the CRC
+    /// check, so its firmware likely uses a different CRC algorithm.
+    ///
+    /// See [`Device::check_crc_compatibility`].
+    #[error("device responses fail the CRC check - firmware may use a different CRC algorithm")]
+    CrcAlgorithmMismatch,
+
+    /// A command did not receive a response within the command timeout.
+    ///
+    /// See [`Device::set_command_timeout`].
+    #[error("timed out waiting for a response")]
+    Timeout,
+
+    /// A blocking call was interrupted using an [`InterruptHandle`].
+    #[error("interrupted")]
+    Interrupted,
+
+    /// The cursor was moved relative to its position, but the position is
+    /// not known.
+    ///
+    /// See [`Device::move_cursor`].
+    #[error("cursor position is unknown")]
+    UnknownCursorPosition,
+
+    /// A string contained a character that the display cannot show.
+    ///
+    /// See [`charset::encode_char`] for the characters that can be shown.
+    #[error("character {0:?} cannot be shown on the display")]
+    UnmappableCharacter(char),
+
+    /// The operation is not available on the device's [`Model`], e.g. an LED
+    /// on a model without LEDs.
+    #[error("not supported by the {0}")]
+    UnsupportedModel(Model),
+}
+
+/// Returns the expected data length of the response to a command, or `None`
+/// if the command is unknown or its response length varies.
+///
+/// A mismatch indicates that the response is from a different firmware
+/// version, or that the stream is out of sync.
+fn expected_response_len(command: &Packet) -> Option<usize> {
+    match command.packet_type() {
+        // Ping echoes its payload.
+        0x00 => Some(command.data().len()),
+        // Read user flash: the whole area.
+        0x03 => Some(limits::USER_FLASH_LEN),
+        // Read keypad: the pressed, pressed since and released since masks.
+        0x18 => Some(3),
+        // Read LCD memory: the address and 8 bytes.
+        0x0a => Some(9),
+        // Read GPIO: the index, state, requested level and function.
+        0x23 => Some(4),
+        // Commands that only acknowledge.
+        0x02 | 0x04 | 0x05 | 0x06 | 0x09 | 0x0b | 0x15 | 0x0c | 0x0d | 0x0e | 0x17 | 0x1c
+        | 0x1d | 0x1f | 0x22 => Some(0),
+        _ => None,
+    }
+}
+
+/// Calls `f` until it succeeds, up to `attempts` times, sleeping between
+/// attempts with exponential backoff.
+fn retry<T>(
+    attempts: u32,
+    mut backoff: Duration,
+    mut f: impl FnMut() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt >= attempts => return Err(err),
+            Err(err) => {
+                log::debug!(
+                    "attempt {} failed, retrying in {:?}: {}",
+                    attempt,
+                    backoff,
+                    err
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Checks that `value` is within `0..limit`, returning `InvalidArgument` for
+/// the named field if it is not.
+pub(crate) fn check_range(field: &'static str, value: u8, limit: u8) -> Result<(), Error> {
+    if value < limit {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument {
+            field,
+            value: value.into(),
+            min: 0,
+            max: usize::from(limit) - 1,
+        })
+    }
+}
+
+/// Checks that `value` is within `min..=max`, returning `InvalidArgument` for
+/// the named field if it is not.
+pub(crate) fn check_bounds(
+    field: &'static str,
+    value: usize,
+    min: usize,
+    max: usize,
+) -> Result<(), Error> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument {
+            field,
+            value,
+            min,
+            max,
+        })
+    }
+}
+
+/// Encodes `text` with [`charset::encode_char`], failing on the first
+/// character that the display cannot show.
+pub(crate) fn encode_str(text: &str) -> Result<Vec<u8>, Error> {
+    text.chars()
+        .map(|c| charset::encode_char(c).ok_or(Error::UnmappableCharacter(c)))
+        .collect()
+}
+
+/// Returns `InvalidArgument` if `data` is longer than `max` bytes.
+///
+/// Methods that build packets from caller-provided data use this so that an
+/// oversized payload is reported as an error, rather than reaching the
+/// assertion in [`Packet::new`].
+pub(crate) fn check_len(field: &'static str, data: &[u8], max: usize) -> Result<(), Error> {
+    if data.len() <= max {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument {
+            field,
+            value: data.len(),
+            min: 0,
+            max,
+        })
+    }
+}
+
+impl From<WritePacketError> for Error {
+    fn from(err: WritePacketError) -> Self {
+        match err {
+            WritePacketError::Io(err) => Self::Io(err),
+            WritePacketError::InvalidLength { len } => Self::InvalidArgument {
+                field: "data",
+                value: len,
+                min: 0,
+                max: MAX_DATA_LEN,
+            },
+        }
+    }
+}
+
+impl From<ReadPacketError> for Error {
+    fn from(err: ReadPacketError) -> Self {
+        match err {
+            ReadPacketError::Io(err) => Self::Io(err),
+            ReadPacketError::InvalidPacket => Self::InvalidRead,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_status() {
+        let data = [
+            0x00, 0x01, 0x00, 0x00, 0x80, 0x3f, 0x01, 0x00, 0x0f, 0x01, 0x01, 0x01, 0x01, 0x78,
+            0x64, 0x32,
+        ];
+        let status = DeviceStatus::from_data(&data).expect("decode failed");
+        assert_eq!(status.fan_reporting, 0);
+        assert_eq!(status.temperature_reporting, 0x8000_0001);
+        assert_eq!(status.key_press_mask, 0x3f);
+        assert_eq!(status.key_release_mask, 0x01);
+        assert_eq!(status.atx_flags, 0);
+        assert_eq!(status.watchdog_counter, 15);
+        assert!(status.watchdog_armed());
+        assert_eq!(status.fan_glitch_delay, [1; 4]);
+        assert_eq!(status.contrast, 120);
+        assert_eq!(status.backlight, 100);
+        assert_eq!(status.keypad_backlight, Some(50));
+    }
+
+    #[test]
+    fn decode_status_without_keypad_backlight() {
+        let data = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x78, 0x64];
+        let status = DeviceStatus::from_data(&data).expect("decode failed");
+        assert!(!status.watchdog_armed());
+        assert_eq!(status.keypad_backlight, None);
+    }
+
+    #[test]
+    fn decode_status_too_short() {
+        assert_eq!(DeviceStatus::from_data(&[0; 14]), None);
+    }
+
+    #[test]
+    fn display_key() {
+        let names: Vec<String> = Key::ALL.iter().map(Key::to_string).collect();
+        assert_eq!(names, ["Up", "Down", "Left", "Right", "Enter", "Exit"]);
+    }
+
+    #[test]
+    fn display_report() {
+        let pressed = Report::KeyActivity {
+            key: Key::Up,
+            pressed: true,
+        };
+        let released = Report::KeyActivity {
+            key: Key::Enter,
+            pressed: false,
+        };
+        assert_eq!(pressed.to_string(), "Up pressed");
+        assert_eq!(released.to_string(), "Enter released");
+    }
+
+    #[test]
+    fn decode_key_masks() {
+        assert_eq!(Key::from_mask(0x00), []);
+        assert_eq!(Key::from_mask(0x3f), Key::ALL);
+        assert_eq!(Key::from_mask(0x21), [Key::Up, Key::Down]);
+        assert_eq!(Key::from_mask(0x1a), [Key::Left, Key::Right, Key::Enter]);
+    }
+
+    #[test]
+    fn key_reporting_config_from_status() {
+        let (mut device, mock) = mock::device();
+        let mut data = [0; 15];
+        data[5] = 0x09;
+        data[6] = 0x04;
+        mock.respond(0x1e, &data);
+        let config = device.key_reporting_config().unwrap();
+        assert_eq!(config.press, [Key::Up, Key::Left]);
+        assert_eq!(config.release, [Key::Exit]);
+    }
+
+    #[test]
+    fn key_reporting_config_falls_back_to_cache() {
+        let (mut device, mock) = mock::device();
+        mock.respond_error(0x1e);
+        assert!(device.key_reporting_config().is_err());
+
+        device
+            .configure_key_reporting(&[Key::Enter], &[Key::Enter, Key::Exit])
+            .unwrap();
+        mock.respond_error(0x1e);
+        let config = device.key_reporting_config().unwrap();
+        assert_eq!(config.press, [Key::Enter]);
+        assert_eq!(config.release, [Key::Enter, Key::Exit]);
+    }
+
+    fn assert_invalid_argument(result: Result<(), Error>, field: &str, value: usize, max: usize) {
+        match result {
+            Err(Error::InvalidArgument {
+                field: f,
+                value: v,
+                max: m,
+                ..
+            }) => assert_eq!((f, v, m), (field, value, max)),
+            other => panic!("expected InvalidArgument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn set_text_out_of_bounds() {
+        let (mut device, mock) = mock::device();
+        assert_invalid_argument(device.set_text(4, 0, b"x"), "row", 4, 3);
+        assert_invalid_argument(device.set_text(0, 20, b"x"), "col", 20, 19);
+        assert_invalid_argument(device.set_text(0, 18, b"xyz"), "text", 3, 2);
+        assert!(mock.sent().is_empty());
+
+        // Ending exactly at the right edge is allowed.
+        device.set_text(0, 18, b"xy").unwrap();
+        device.set_text_truncating(1, 18, b"xyz").unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x1f, b"\x12\x00xy"),
+                Packet::new(0x1f, b"\x12\x01xy"),
+            ]
+        );
+    }
+
+    #[test]
+    fn fill_rows() {
+        let (mut device, mock) = mock::device();
+        device.fill_rows(b"test").unwrap();
+        let sent = mock.sent();
+        assert_eq!(sent.len(), usize::from(NUM_ROWS));
+        for (row, packet) in sent.iter().enumerate() {
+            assert_eq!(packet.packet_type(), 0x1f);
+            assert_eq!(packet.data()[..2], [0, row as u8]);
+            assert_eq!(&packet.data()[2..], b"test                ");
+        }
+    }
+
+    #[test]
+    fn test_pattern_restores_settings() {
+        let (mut device, mock) = mock::device();
+        let mut status = [0; 16];
+        status[13..].copy_from_slice(&[90, 70, 40]);
+        mock.respond(0x1e, &status);
+        device.test_pattern().unwrap();
+
+        let sent = mock.sent();
+        assert_eq!(sent[0], Packet::new(0x1e, &[]));
+        let contrasts: Vec<u8> = sent
+            .iter()
+            .filter(|packet| packet.packet_type() == 0x0d)
+            .map(|packet| packet.data()[0])
+            .collect();
+        assert_eq!(contrasts.len(), 20);
+        assert_eq!(contrasts[..2], [60, 65]);
+        assert_eq!(contrasts[18..], [150, 90]);
+        let leds = sent
+            .iter()
+            .filter(|packet| packet.packet_type() == 0x22)
+            .count();
+        assert_eq!(leds, 24);
+        assert_eq!(
+            sent[sent.len() - 2..],
+            [Packet::new(0x06, &[]), Packet::new(0x0e, &[70, 40])]
+        );
+        assert_eq!(mock.elapsed(), Duration::from_secs(17));
+
+        // Known settings are not read again.
+        let sent_before = mock.sent().len();
+        device.test_pattern().unwrap();
+        assert_eq!(mock.sent()[sent_before], Packet::new(0x06, &[]));
+    }
+
+    #[test]
+    fn test_pattern_needs_previous_settings() {
+        let (mut device, mock) = mock::device();
+        mock.respond(0x1e, &[0; 4]);
+        assert!(matches!(device.test_pattern(), Err(Error::InvalidRead)));
+        assert_eq!(mock.sent(), [Packet::new(0x1e, &[])]);
+    }
+
+    #[test]
+    fn fill_rows_too_long() {
+        let (mut device, mock) = mock::device();
+        assert_invalid_argument(device.fill_rows(&[b'x'; 21]), "text", 21, 20);
+        assert!(mock.sent().is_empty());
+    }
+
+    #[test]
+    fn dry_run_captures_packets() {
+        let mut device = Device::dry_run();
+        device.clear_screen().unwrap();
+        assert_eq!(device.ping(b"hello").unwrap(), b"hello");
+        device.set_text(1, 2, b"abc").unwrap();
+        assert_eq!(
+            device.captured_packets(),
+            [
+                Packet::new(0x06, &[]),
+                Packet::new(0x00, b"hello"),
+                Packet::new(0x1f, &[2, 1, b'a', b'b', b'c']),
+            ]
+        );
+        assert!(device.poll_report().unwrap().is_none());
+    }
+
+    #[test]
+    fn apply_frame_sends_changes_only() {
+        let (mut device, mock) = mock::device();
+        let frame = Frame::new()
+            .row(0, b"hello")
+            .unwrap()
+            .row(2, b"world")
+            .unwrap()
+            .cursor_position(1, 5)
+            .unwrap()
+            .led(3, LedColor::new(100, 0))
+            .unwrap()
+            .backlight(50, 50);
+        device.apply_frame(&frame).unwrap();
+        assert_eq!(mock.sent().len(), 6);
+        // Sent as a batch, before reading any of the responses.
+        assert_eq!(mock.reads_before_sent(), [0; 6]);
+        assert!(mock.reads() > 0);
+
+        // Unchanged frame
+        device.apply_frame(&frame).unwrap();
+        assert_eq!(mock.sent().len(), 6);
+
+        // One row changed, the rest unspecified
+        let frame = Frame::new().row(2, b"there").unwrap();
+        device.apply_frame(&frame).unwrap();
+        let sent = mock.sent();
+        assert_eq!(sent.len(), 7);
+        assert_eq!(sent[6].data(), b"\x00\x02there               ");
+    }
+
+    #[test]
+    fn apply_frame_failure_is_not_recorded() {
+        let (mut device, mock) = mock::device();
+        let frame = Frame::new().row(0, b"hello").unwrap().backlight(50, 50);
+        mock.respond_error(0x0e);
+        assert!(matches!(
+            device.apply_frame(&frame),
+            Err(Error::ReturnedError { command: 0x0e, .. })
+        ));
+        assert_eq!(mock.sent().len(), 2);
+
+        device.apply_frame(&frame).unwrap();
+        assert_eq!(mock.sent()[2..], mock.sent()[..2]);
+    }
+
+    #[test]
+    fn next_report_or_returns_report() {
+        let (mut device, mock) = mock::device();
+        mock.queue(&Packet::new(0x80, &[5]));
+        let default = Report::KeyActivity {
+            key: Key::Exit,
+            pressed: false,
+        };
+        let report = device
+            .next_report_or(Duration::from_millis(20), default)
+            .unwrap();
+        assert_eq!(
+            report,
+            Report::KeyActivity {
+                key: Key::Enter,
+                pressed: true
+            }
+        );
+    }
+
+    #[test]
+    fn peek_reports_does_not_consume() {
+        let (mut device, mock) = mock::device();
+        assert_eq!(device.peek_reports().len(), 0);
+
+        // Reports read while waiting for a response are buffered.
+        mock.queue(&Packet::new(0x80, &[5]));
+        device.ping(b"hi").unwrap();
+        let pressed = Report::KeyActivity {
+            key: Key::Enter,
+            pressed: true,
+        };
+        assert_eq!(device.peek_reports().collect::<Vec<_>>(), [&pressed]);
+        assert_eq!(device.peek_reports().len(), 1);
+
+        assert_eq!(device.poll_report().unwrap(), Some(pressed));
+        assert_eq!(device.peek_reports().len(), 0);
+    }
+
+    #[test]
+    fn buffered_report_keeps_receive_time() {
+        let (mut device, mock) = mock::device();
+        mock.advance(Duration::from_millis(5));
+        let before = mock.now();
+        mock.queue(&Packet::new(0x80, &[5]));
+        device.ping(b"hi").unwrap();
+        mock.advance(Duration::from_millis(20));
+
+        let (received, report) = device.poll_report_timestamped().unwrap().unwrap();
+        assert_eq!(received, before);
+        assert_eq!(
+            report,
+            Report::KeyActivity {
+                key: Key::Enter,
+                pressed: true
+            }
+        );
+        assert_eq!(device.poll_report_timestamped().unwrap(), None);
+    }
+
+    #[test]
+    fn send_no_wait_does_not_read() {
+        let (mut device, mock) = mock::device();
+        device.send_no_wait(0x0e, &[100]).unwrap();
+        assert_eq!(mock.sent(), [Packet::new(0x0e, &[100])]);
+        assert_eq!(mock.reads(), 0);
+
+        // The response is discarded when polling for reports.
+        assert_eq!(device.discarded_packets(), 0);
+        assert_eq!(device.poll_report().unwrap(), None);
+        assert!(mock.reads() > 0);
+        assert_eq!(device.discarded_packets(), 1);
+
+        assert_invalid_argument(device.send_no_wait(0x40, &[]), "command", 0x40, 0x3f);
+        assert_invalid_argument(device.send_no_wait(0x1f, &[0; 23]), "data", 23, 22);
+    }
+
+    #[test]
+    fn raw_transact() {
+        let (mut device, mock) = mock::device();
+        mock.queue(&Packet::new(0x80, &[1]));
+        mock.respond(0x01, b"CFA635:h1.5,u1v2");
+        assert_eq!(device.raw_transact(0x01, &[]).unwrap(), b"CFA635:h1.5,u1v2");
+        assert_eq!(mock.sent(), [Packet::new(0x01, &[])]);
+        assert!(device.poll_report().unwrap().is_some());
+
+        mock.respond_error(0x3a);
+        assert!(matches!(
+            device.raw_transact(0x3a, &[1, 2]),
+            Err(Error::ReturnedError { command: 0x3a, data }) if data.is_empty()
+        ));
+        mock.respond_with(0x3a, Packet::new(0xfa, &[7]));
+        assert!(matches!(
+            device.raw_transact(0x3a, &[]),
+            Err(Error::ReturnedError { command: 0x3a, data }) if data == [7]
+        ));
+        assert_invalid_argument(
+            device.raw_transact(0x40, &[]).map(drop),
+            "command",
+            0x40,
+            0x3f,
+        );
+        assert_invalid_argument(
+            device.raw_transact(0x1f, &[0; 23]).map(drop),
+            "data",
+            23,
+            22,
+        );
+    }
+
+    #[test]
+    fn crc_compatibility() {
+        let (mut device, mock) = mock::device();
+        device.check_crc_compatibility().unwrap();
+
+        let mut bad_crc = Packet::new(0x40, b"crc probe").to_bytes();
+        *bad_crc.last_mut().unwrap() ^= 0xff;
+        for _ in 0..3 {
+            mock.respond_bytes(0x00, bad_crc.clone());
+        }
+        assert!(matches!(
+            device.check_crc_compatibility(),
+            Err(Error::CrcAlgorithmMismatch)
+        ));
+
+        // A corrupted payload is not a CRC mismatch.
+        let mut corrupted = Packet::new(0x40, b"crc probe").to_bytes();
+        corrupted[2] ^= 0xff;
+        mock.respond_bytes(0x00, corrupted);
+        assert!(matches!(
+            device.check_crc_compatibility(),
+            Err(Error::InvalidRead)
+        ));
+
+        // One good response out of several is enough.
+        mock.respond_bytes(0x00, bad_crc);
+        device.check_crc_compatibility().unwrap();
+        assert_eq!(mock.sent().len(), 7);
+    }
+
+    #[test]
+    fn held_keys() {
+        let (mut device, mock) = mock::device();
+        assert!(!device.is_key_held(Key::Enter));
+
+        mock.queue(&Packet::new(0x80, &[5]));
+        mock.queue(&Packet::new(0x80, &[1]));
+        device.poll_report().unwrap();
+        assert!(device.is_key_held(Key::Enter));
+        assert!(!device.is_key_held(Key::Up));
+        device.poll_report().unwrap();
+        assert!(device.is_key_held(Key::Enter));
+        assert!(device.is_key_held(Key::Up));
+
+        mock.queue(&Packet::new(0x80, &[11]));
+        device.poll_report().unwrap();
+        assert!(!device.is_key_held(Key::Enter));
+        assert!(device.is_key_held(Key::Up));
+    }
+
+    #[test]
+    fn command_timeout() {
+        let (mut device, mock) = mock::device();
+        device.set_command_timeout(Duration::ZERO);
+        for _ in 0..5 {
+            mock.queue(&Packet::new(0x80, &[5]));
+        }
+        assert!(matches!(device.clear_screen(), Err(Error::Timeout)));
+
+        device.set_command_timeout(None);
+        device.clear_screen().unwrap();
+        assert_eq!(device.peek_reports().len(), 5);
+    }
+
+    #[test]
+    fn command_timeout_during_report_flood() {
+        let (mut device, mock) = mock::device();
+        device.set_command_timeout(Duration::from_millis(35));
+        // Each packet is read as its header and then the rest, 20 ms in all.
+        mock.set_read_delay(Duration::from_millis(10));
+        for _ in 0..5 {
+            mock.queue(&Packet::new(0x80, &[5]));
+        }
+        assert!(matches!(device.clear_screen(), Err(Error::Timeout)));
+        assert_eq!(device.peek_reports().len(), 2);
+        assert_eq!(mock.elapsed(), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn set_line_pads_row() {
+        let (mut device, mock) = mock::device();
+        device.set_line(1, b"line two").unwrap();
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(0x1f, b"\x00\x01line two            ")]
+        );
+        assert_invalid_argument(device.set_line(4, b""), "line", 4, 3);
+        assert_invalid_argument(device.set_line(0, &[b'x'; 21]), "text", 21, 20);
+    }
+
+    /// Scripts the DDRAM reads of `read_screen`, given the contents of rows 0
+    /// and 2, then rows 1 and 3.
+    fn respond_ddram(mock: &mock::MockHandle, regions: [&[u8; 40]; 2]) {
+        for (base, region) in [0x80, 0xc0].into_iter().zip(regions) {
+            for (i, chunk) in (0..).zip(region.chunks(8)) {
+                let mut data = vec![base + 8 * i];
+                data.extend_from_slice(chunk);
+                mock.respond(0x0a, &data);
+            }
+        }
+    }
+
+    #[test]
+    fn read_screen_ddram() {
+        let (mut device, mock) = mock::device();
+        // As captured from a device.
+        respond_ddram(
+            &mock,
+            [
+                b"Row zero            Row two             ",
+                b"Row one             Row three           ",
+            ],
+        );
+
+        let screen = device.read_screen().unwrap();
+        assert_eq!(screen.row(0).unwrap(), b"Row zero            ");
+        assert_eq!(screen.row(1).unwrap(), b"Row one             ");
+        assert_eq!(screen.row(2).unwrap(), b"Row two             ");
+        assert_eq!(screen.row(3).unwrap(), b"Row three           ");
+
+        let addresses: Vec<u8> = mock.sent().iter().map(|p| p.data()[0]).collect();
+        assert_eq!(
+            addresses,
+            [0x80, 0x88, 0x90, 0x98, 0xa0, 0xc0, 0xc8, 0xd0, 0xd8, 0xe0]
+        );
+    }
+
+    #[test]
+    fn discarded_packets_counted() {
+        let (mut device, mock) = mock::device();
+        mock.queue(&Packet::new(0x4e, &[]));
+        mock.queue(&Packet::new(0x80, &[5]));
+        mock.queue(&Packet::new(0x4c, &[]));
+        assert!(device.poll_report().unwrap().is_some());
+        assert_eq!(device.discarded_packets(), 1);
+        assert_eq!(device.poll_report().unwrap(), None);
+        assert_eq!(device.discarded_packets(), 2);
+    }
+
+    #[test]
+    fn report_buffer_limit() {
+        let press = |code| Packet::new(0x80, &[code]);
+        let (mut device, mock) = mock::device();
+        device.set_report_buffer_limit(2, ReportOverflow::DropOldest);
+        for code in 1..=3 {
+            mock.queue(&press(code));
+        }
+        device.clear_screen().unwrap();
+        assert_eq!(device.dropped_reports_count(), 1);
+        let keys: Vec<Report> = device.reports().map(Result::unwrap).collect();
+        assert_eq!(
+            keys,
+            [Report::from_raw(&press(2)), Report::from_raw(&press(3))].map(Option::unwrap)
+        );
+
+        device.set_report_buffer_limit(2, ReportOverflow::DropNewest);
+        for code in 4..=6 {
+            mock.queue(&press(code));
+        }
+        device.clear_screen().unwrap();
+        assert_eq!(device.dropped_reports_count(), 2);
+        device.set_report_buffer_limit(1, ReportOverflow::DropNewest);
+        assert_eq!(device.dropped_reports_count(), 3);
+        assert_eq!(device.poll_report().unwrap(), Report::from_raw(&press(4)));
+        assert_eq!(device.poll_report().unwrap(), None);
+    }
+
+    #[test]
+    fn contrast_for_temperature() {
+        let (mut device, mock) = mock::device();
+        device.set_contrast_for_temperature(25.0).unwrap();
+        device.set_contrast_curve(ContrastCurve::new(vec![(0.0, 200)]));
+        device.set_contrast_for_temperature(25.0).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(0x0d, &[120]), Packet::new(0x0d, &[200])]
+        );
+    }
+
+    #[test]
+    fn verify_boot_state() {
+        let (mut device, mock) = mock::device();
+        let status = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x78,
+            0x64, 0x32,
+        ];
+        let mut expected = BootState {
+            contrast: Some(120),
+            backlight: Some(100),
+            keypad_backlight: Some(50),
+            key_reporting: Some(KeyReporting {
+                press: vec![
+                    Key::Exit,
+                    Key::Up,
+                    Key::Down,
+                    Key::Left,
+                    Key::Right,
+                    Key::Enter,
+                ],
+                release: vec![],
+            }),
+            ..BootState::default()
+        };
+        mock.respond(0x1e, &status);
+        assert_eq!(device.verify_boot_state(&expected).unwrap(), []);
+
+        let mut hello = Screen::blank();
+        hello.rows[1][..5].copy_from_slice(b"Hello");
+        expected.screen = Some(hello.clone());
+        expected.backlight = Some(80);
+        respond_ddram(&mock, [&[b' '; 40], &[b' '; 40]]);
+        mock.respond(0x1e, &status);
+        assert_eq!(
+            device.verify_boot_state(&expected).unwrap(),
+            [
+                BootStateMismatch::Screen {
+                    expected: hello,
+                    actual: Screen::blank(),
+                },
+                BootStateMismatch::Backlight {
+                    expected: 80,
+                    actual: 100,
+                },
+            ]
+        );
+
+        // Nothing is read if nothing is expected.
+        let sent = mock.sent().len();
+        assert_eq!(device.verify_boot_state(&BootState::default()).unwrap(), []);
+        assert_eq!(mock.sent().len(), sent);
+    }
+
+    #[test]
+    fn verify_custom_characters() {
+        let (mut device, mock) = mock::device();
+        let arrow = [0x04, 0x0e, 0x15, 0x04, 0x04, 0x04, 0x04, 0x00];
+        let mut expected = BootState::default();
+        expected.custom_characters[1] = Some(arrow);
+        // Higher bits are ignored on both sides.
+        expected.custom_characters[6] = Some(arrow.map(|row| row | 0xc0));
+        let mut slot1 = vec![0x48];
+        slot1.extend(arrow.map(|row| row | 0x80));
+        mock.respond(0x0a, &slot1);
+        mock.respond(0x0a, &[0x70, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            device.verify_boot_state(&expected).unwrap(),
+            [BootStateMismatch::CustomCharacter {
+                index: 6,
+                expected: arrow,
+                actual: [0; 8],
+            }]
+        );
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(0x0a, &[0x48]), Packet::new(0x0a, &[0x70])]
+        );
+
+        mock.respond(0x0a, &[0x40, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(matches!(
+            device.read_custom_character(3),
+            Err(Error::InvalidRead)
+        ));
+        assert_invalid_argument(device.read_custom_character(8).map(drop), "index", 8, 7);
+    }
+
+    #[test]
+    fn discard_reports_on_connect() {
+        let (mut device, mock) = mock::device();
+        for _ in 0..3 {
+            mock.queue(&Packet::new(0x80, &[5]));
+        }
+        assert_eq!(device.discard_reports().unwrap(), 3);
+        device.clear_screen().unwrap();
+        assert_eq!(mock.sent(), [Packet::new(0x06, &[])]);
+        assert_eq!(device.peek_reports().len(), 0);
+        assert_eq!(device.poll_report().unwrap(), None);
+
+        // Buffered reports are discarded too.
+        mock.queue(&Packet::new(0x80, &[5]));
+        device.clear_screen().unwrap();
+        assert_eq!(device.discard_reports().unwrap(), 1);
+        assert_eq!(device.poll_report().unwrap(), None);
+    }
+
+    #[test]
+    fn led_newtype() {
+        assert_eq!(Led::new(0).map(Led::index), Some(0));
+        assert_eq!(Led::new(3).map(u8::from), Some(3));
+        assert_eq!(Led::new(4), None);
+        assert_eq!(Led::ALL.map(Led::index), [0, 1, 2, 3]);
+        assert_eq!(2.into_led().unwrap(), Led::ALL[2]);
+        assert_invalid_argument(4.into_led().map(drop), "index", 4, 3);
+
+        let (mut device, mock) = mock::device();
+        device.set_led(Led::ALL[3], 0, 100).unwrap();
+        device.set_led(3, 0, 100).unwrap();
+        let sent = mock.sent();
+        assert_eq!(sent[..2], sent[2..]);
+    }
+
+    #[test]
+    fn port_name_without_port() {
+        let (device, _mock) = mock::device();
+        assert_eq!(device.port_name(), None);
+        assert_eq!(device.baud_rate(), None);
+        assert!(device.usb_info().unwrap().is_none());
+        assert_eq!(Device::dry_run().port_name(), None);
+    }
+
+    #[test]
+    fn oversized_payloads() {
+        let (mut device, mock) = mock::device();
+        let long = [b'x'; 100];
+        assert_invalid_argument(device.send_no_wait(0x1f, &long), "data", 100, 22);
+        assert_invalid_argument(device.fill_rows(&long), "text", 100, 20);
+        assert_invalid_argument(device.set_line(0, &long), "text", 100, 20);
+        assert_invalid_argument(Frame::new().row(0, &long).map(drop), "text", 100, 20);
+        assert!(mock.sent().is_empty());
+
+        assert_invalid_argument(device.set_text(0, 0, &long), "text", 100, 20);
+        assert_invalid_argument(device.set_text(0, 15, b"123456"), "text", 6, 5);
+        assert!(mock.sent().is_empty());
+
+        // These are documented to truncate instead.
+        assert_eq!(device.ping(&long).unwrap().len(), limits::MAX_PING_LEN);
+        device.set_text_truncating(0, 0, &long).unwrap();
+        assert_eq!(mock.sent()[1].data().len(), MAX_DATA_LEN);
+        device.set_text_truncating(2, 15, b"123456").unwrap();
+        assert_eq!(mock.sent()[2], Packet::new(0x1f, b"\x0f\x0212345"));
+    }
+
+    #[test]
+    fn tail_scrolls() {
+        let (mut device, mock) = mock::device();
+        let input = "one\ntwo\r\nthree\n";
+        device
+            .tail(input.as_bytes(), 2, LineOverflow::Truncate)
+            .unwrap();
+        let sent = mock.sent();
+        let rows: Vec<&[u8]> = sent.iter().map(|p| &p.data()[..7]).collect();
+        assert_eq!(
+            rows,
+            [
+                b"\x00\x00one  ",
+                b"\x00\x01two  ",
+                b"\x00\x00two  ",
+                b"\x00\x01three",
+            ]
+        );
+    }
+
+    #[test]
+    fn tail_overflow() {
+        let (mut device, mock) = mock::device();
+        let input = "abcdefghijklmnopqrstuvwxyz\n";
+        device
+            .tail(input.as_bytes(), 4, LineOverflow::Wrap)
+            .unwrap();
+        let sent = mock.sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(&sent[0].data()[2..], b"abcdefghijklmnopqrst");
+        assert_eq!(&sent[1].data()[2..], b"uvwxyz              ");
+
+        let (mut device, mock) = mock::device();
+        device
+            .tail(input.as_bytes(), 4, LineOverflow::Truncate)
+            .unwrap();
+        assert_eq!(mock.sent().len(), 1);
+        assert_eq!(&mock.sent()[0].data()[2..], b"abcdefghijklmnopqrst");
+
+        assert_invalid_argument(device.tail(&b""[..], 5, LineOverflow::Wrap), "lines", 5, 4);
+    }
+
+    #[test]
+    fn apply_config() {
+        let (mut device, mock) = mock::device();
+        let config = DeviceConfig {
+            contrast: Some(110),
+            backlight: Some(80),
+            keypad_backlight: Some(20),
+            cursor_style: Some(CursorStyle::BlinkingBlock),
+            cursor_position: Some((1, 4)),
+            key_reporting: Some(KeyReporting {
+                press: vec![Key::Up, Key::Down],
+                release: vec![],
+            }),
+            custom_chars: Some(vec![(2, [1, 2, 3, 4, 5, 6, 7, 8])]),
+            save_boot_state: true,
+        };
+        device.apply_config(&config).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x0d, &[110]),
+                Packet::new(0x0e, &[80, 20]),
+                Packet::new(0x0c, &[1]),
+                Packet::new(0x0b, &[4, 1]),
+                Packet::new(0x17, &[0x21, 0x00]),
+                Packet::new(0x09, &[2, 1, 2, 3, 4, 5, 6, 7, 8]),
+                Packet::new(0x04, &[]),
+            ]
+        );
+
+        // Nothing is sent if validation fails.
+        let invalid = DeviceConfig {
+            cursor_position: Some((0, 20)),
+            ..config.clone()
+        };
+        assert_invalid_argument(device.apply_config(&invalid), "col", 20, 19);
+        let invalid = DeviceConfig {
+            custom_chars: Some(vec![(0, [0; 8]), (8, [0; 8])]),
+            ..config
+        };
+        assert_invalid_argument(device.apply_config(&invalid), "index", 8, 7);
+        assert_eq!(mock.sent().len(), 7);
+
+        device.apply_config(&DeviceConfig::default()).unwrap();
+        assert_eq!(mock.sent().len(), 7);
+    }
+
+    #[test]
+    fn read_gpio() {
+        let (mut device, mock) = mock::device();
+        for index in 0..NUM_GPIO_PINS {
+            mock.respond(0x23, &[index, 0x05, 100, 0x0b]);
+        }
+        let states = device.read_all_gpio().unwrap();
+        assert_eq!(states.len(), 13);
+        assert_eq!(
+            states[12],
+            GpioState {
+                index: 12,
+                level: true,
+                fell: false,
+                rose: true,
+                requested_level: 100,
+                function: 0x0b,
+            }
+        );
+        assert!(states.iter().zip(0..).all(|(state, i)| state.index == i));
+        let requested: Vec<u8> = mock.sent().iter().map(|p| p.data()[0]).collect();
+        assert_eq!(requested, (0..13).collect::<Vec<u8>>());
+
+        mock.respond(0x23, &[3, 0, 0, 0]);
+        assert!(matches!(device.read_gpio(4), Err(Error::InvalidRead)));
+        assert_invalid_argument(device.read_gpio(13).map(drop), "index", 13, 12);
+    }
+
+    #[test]
+    fn set_text_sanitized() {
+        let (mut device, mock) = mock::device();
+        device.set_text_sanitized(1, 0, b"a\tb\n\x01").unwrap();
+        device.set_text(1, 0, b"a\tb\n\x01").unwrap();
+        let sent = mock.sent();
+        assert_eq!(sent[0].data(), b"\x00\x01a b \x01");
+        assert_eq!(sent[1].data(), b"\x00\x01a\tb\n\x01");
+    }
+
+    #[test]
+    fn latency_stats() {
+        let ms = Duration::from_millis;
+        let stats =
+            LatencyStats::from_samples(&[ms(2), ms(4), ms(4), ms(4), ms(5), ms(5), ms(7), ms(9)]);
+        assert_eq!(stats.samples, 8);
+        assert_eq!(stats.min, ms(2));
+        assert_eq!(stats.max, ms(9));
+        assert_eq!(stats.mean, ms(5));
+        assert!((stats.stddev.as_secs_f64() - 0.002).abs() < 1e-9);
+    }
+
+    #[test]
+    fn measure_latency() {
+        let (mut device, mock) = mock::device();
+        mock.set_read_delay(Duration::from_millis(5));
+        let stats = device.measure_latency(3).unwrap();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(mock.sent().len(), 3);
+        // Each response is read as its header and then the rest.
+        assert_eq!(mock.reads(), 6);
+        assert_eq!(stats.min, Duration::from_millis(10));
+        assert_eq!(stats.mean, Duration::from_millis(10));
+        assert_eq!(stats.max, Duration::from_millis(10));
+        assert_eq!(mock.elapsed(), Duration::from_millis(30));
+
+        // At least one sample is taken.
+        assert_eq!(device.measure_latency(0).unwrap().samples, 1);
+    }
+
+    #[test]
+    fn connect_handshake() {
+        let (mut device, mock) = mock::device();
+        // Left over from a previous session.
+        mock.queue(&Packet::new(0x46, &[]));
+        mock.respond(0x01, b"CFA635:h1.5,f1.0");
+        let version = device.connect_handshake(true).unwrap();
+        assert_eq!(version.to_string(), "CFA635:h1.5,f1.0");
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x00, b"cfa635 handshake"),
+                Packet::new(0x01, &[]),
+                Packet::new(0x17, &[0, 0]),
+            ]
+        );
+
+        mock.respond(0x01, b"CFA635:h1.5,f1.0");
+        device.connect_handshake(false).unwrap();
+        assert_eq!(mock.sent().len(), 5);
+
+        // Each failing step stops the handshake.
+        mock.respond(0x00, b"something else!!");
+        assert!(matches!(
+            device.connect_handshake(true),
+            Err(Error::InvalidRead)
+        ));
+        mock.respond_error(0x01);
+        assert!(matches!(
+            device.connect_handshake(true),
+            Err(Error::ReturnedError { command: 0x01, .. })
+        ));
+        assert_eq!(mock.sent().len(), 8);
+        mock.respond_bytes(0x00, Vec::new());
+        assert!(matches!(device.connect_handshake(true), Err(Error::Io(_))));
+        assert_eq!(mock.sent().len(), 9);
+    }
+
+    #[test]
+    fn version() {
+        let (mut device, mock) = mock::device();
+        mock.respond(0x01, b"CFA635:h1.5,f1.0");
+        let version = device.version().unwrap();
+        assert_eq!(version.model, "CFA635");
+        assert_eq!(version.hardware, "1.5");
+        assert_eq!(version.firmware, "1.0");
+        assert_eq!(version.to_string(), "CFA635:h1.5,f1.0");
+        assert_eq!(mock.sent(), [Packet::new(0x01, &[])]);
+
+        for bad in [
+            &b"CFA635"[..],
+            b"CFA635:1.5,1.0",
+            b"CFA635:h1.5",
+            b":h,f",
+            b"\xff",
+        ] {
+            mock.respond(0x01, bad);
+            assert!(matches!(device.version(), Err(Error::InvalidRead)));
+        }
+    }
+
+    #[test]
+    fn flush_framebuffer_sends_changes() {
+        let (mut device, mock) = mock::device();
+        let mut fb = Framebuffer::new();
+        fb.write_str(0, 0, "Count: 9").unwrap();
+        device.flush_framebuffer(&fb).unwrap();
+        assert_eq!(mock.sent().len(), usize::from(NUM_ROWS));
+
+        fb.write_str(0, 7, "10").unwrap();
+        fb.write(3, 19, b"x").unwrap();
+        device.flush_framebuffer(&fb).unwrap();
+        device.flush_framebuffer(&fb).unwrap();
+        assert_eq!(
+            mock.sent()[4..],
+            [
+                Packet::new(0x1f, b"\x07\x0010"),
+                Packet::new(0x1f, b"\x13\x03x"),
+            ]
+        );
+
+        // Text written directly is diffed against too.
+        device.set_text(0, 0, b"Total").unwrap();
+        device.flush_framebuffer(&fb).unwrap();
+        assert_eq!(mock.sent()[7], Packet::new(0x1f, b"\x00\x00Count"));
+
+        device.invalidate_cache();
+        device.flush_framebuffer(&fb).unwrap();
+        assert_eq!(mock.sent().len(), 8 + usize::from(NUM_ROWS));
+    }
+
+    #[test]
+    fn redraw_sends_changed_cells() {
+        let (mut device, mock) = mock::device();
+        device.clear_screen().unwrap();
+        device.set_text(1, 0, b"old text").unwrap();
+        device.set_text(2, 4, b"same").unwrap();
+
+        let mut fb = Framebuffer::new();
+        fb.write(0, 17, b"new").unwrap();
+        fb.write(1, 0, b"old").unwrap();
+        fb.write(2, 4, b"same").unwrap();
+        device.redraw(fb.screen()).unwrap();
+        assert_eq!(
+            mock.sent()[3..],
+            [
+                Packet::new(0x1f, b"\x11\x00new"),
+                Packet::new(0x1f, b"\x04\x01    "),
+            ]
+        );
+        assert!(mock.sent()[1..].iter().all(|p| p.packet_type() != 0x06));
+        assert_eq!(device.screen_snapshot(), fb.screen().rows());
+    }
+
+    #[test]
+    fn step_marquee() {
+        let (mut device, mock) = mock::device();
+        let mut marquee = Marquee::new(2, "This line is too long to fit").unwrap();
+        device.step_marquee(&mut marquee).unwrap();
+        device.step_marquee(&mut marquee).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x1f, b"\x00\x02This line is too lon"),
+                Packet::new(0x1f, b"\x00\x02his line is too long"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_iterator() {
+        let (mut device, mock) = mock::device();
+        mock.queue(&Packet::new(0x80, &[1]));
+        mock.queue(&Packet::new(0x80, &[7]));
+        let reports: Vec<Report> = device.reports().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            reports,
+            [
+                Report::KeyActivity {
+                    key: Key::Up,
+                    pressed: true
+                },
+                Report::KeyActivity {
+                    key: Key::Up,
+                    pressed: false
+                },
+            ]
+        );
+        assert_eq!(device.reports().count(), 0);
+
+        // A truncated packet times out.
+        mock.respond_bytes(0x00, vec![0x80, 0x01]);
+        device.send_no_wait(0x00, &[]).unwrap();
+        let results: Vec<_> = device.reports().collect();
+        assert!(matches!(results[..], [Err(Error::Io(_))]));
+    }
+
+    #[test]
+    fn set_str() {
+        let (mut device, mock) = mock::device();
+        device.set_str(1, 2, "Temp: 25%").unwrap();
+        assert_eq!(mock.sent(), [Packet::new(0x1f, b"\x02\x01Temp: 25%")]);
+        device.set_str(2, 0, "25°C ↑").unwrap();
+        assert_eq!(mock.sent()[1], Packet::new(0x1f, b"\x00\x0225\xb0C \x18"));
+        assert!(matches!(
+            device.set_str(0, 0, "$5"),
+            Err(Error::UnmappableCharacter('$'))
+        ));
+        assert!(matches!(
+            device.set_str(0, 0, "café"),
+            Err(Error::UnmappableCharacter('é'))
+        ));
+        assert_eq!(mock.sent().len(), 2);
+    }
+
+    #[test]
+    fn read_keypad() {
+        let (mut device, mock) = mock::device();
+        mock.respond(0x18, &[0x01 | 0x20, 0x02, 0x04 | 0x08]);
+        assert_eq!(
+            device.read_keypad().unwrap(),
+            KeypadState {
+                currently_pressed: vec![Key::Up, Key::Down],
+                presses_since_last_poll: vec![Key::Enter],
+                releases_since_last_poll: vec![Key::Left, Key::Exit],
+            }
+        );
+        assert_eq!(mock.sent(), [Packet::new(0x18, &[])]);
+    }
+
+    #[test]
+    fn user_flash() {
+        let (mut device, mock) = mock::device();
+        device.write_user_flash(b"SN-0042").unwrap();
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(0x02, b"SN-0042\0\0\0\0\0\0\0\0\0")]
+        );
+        assert_invalid_argument(device.write_user_flash(&[0; 17]), "data", 17, 16);
+
+        mock.respond(0x03, b"0123456789abcdef");
+        assert_eq!(device.read_user_flash().unwrap(), b"0123456789abcdef");
+        mock.respond(0x03, b"short");
+        assert!(matches!(device.read_user_flash(), Err(Error::InvalidRead)));
+    }
+
+    #[test]
+    fn reboot_tolerates_missing_response() {
+        let (mut device, mock) = mock::device();
+        device.reboot().unwrap();
+        mock.respond_bytes(0x05, Vec::new());
+        device.reboot().unwrap();
+        assert_eq!(mock.sent(), vec![Packet::new(0x05, &[8, 18, 99]); 2]);
+
+        mock.respond_error(0x05);
+        assert!(matches!(device.reboot(), Err(Error::ReturnedError { .. })));
+    }
+
+    #[test]
+    fn set_custom_character() {
+        let (mut device, mock) = mock::device();
+        let bitmap = [0x04, 0x02, 0x3f, 0x02, 0x04, 0x00, 0x00, 0x00];
+        device.set_custom_character(7, bitmap).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(
+                0x09,
+                &[7, 0x04, 0x02, 0x3f, 0x02, 0x04, 0, 0, 0]
+            )]
+        );
+        assert_invalid_argument(device.set_custom_character(8, bitmap), "index", 8, 7);
+    }
+
+    #[test]
+    fn wait_report() {
+        let (mut device, mock) = mock::device();
+        assert_eq!(
+            device.wait_report(Some(Duration::from_millis(25))).unwrap(),
+            None
+        );
+        assert_eq!(mock.elapsed(), Duration::from_millis(25));
+
+        mock.queue(&Packet::new(0x80, &[5]));
+        assert_eq!(
+            device.wait_report(None).unwrap(),
+            Some(Report::KeyActivity {
+                key: Key::Enter,
+                pressed: true
+            })
+        );
+        assert_eq!(mock.elapsed(), Duration::from_millis(25));
+    }
+
+    #[test]
+    fn next_report_or_times_out() {
+        let (mut device, mock) = mock::device();
+        let default = Report::KeyActivity {
+            key: Key::Exit,
+            pressed: false,
+        };
+        let report = device
+            .next_report_or(Duration::from_millis(20), default.clone())
+            .unwrap();
+        assert_eq!(report, default);
+        assert_eq!(mock.elapsed(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn retry_until_success() {
+        let mut calls = 0;
+        let result = retry(5, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 {
+                Err(Error::InvalidRead)
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn retries_corrupted_response() {
+        let corrupted = || {
+            let mut bytes = Packet::new(0x58, &[0, 0, 0]).to_bytes();
+            bytes[3] ^= 0x01;
+            bytes
+        };
+        let (mut device, mock) = mock::device();
+        mock.respond_bytes(0x18, corrupted());
+        assert!(matches!(device.read_keypad(), Err(Error::InvalidRead)));
+
+        device.set_retries(1, Duration::from_millis(1));
+        mock.respond_bytes(0x18, corrupted());
+        mock.respond(0x18, &[0, 0, 0]);
+        device.read_keypad().unwrap();
+        assert_eq!(mock.sent().len(), 3);
+
+        mock.respond_bytes(0x18, corrupted());
+        mock.respond_bytes(0x18, corrupted());
+        assert!(matches!(device.read_keypad(), Err(Error::InvalidRead)));
+        assert_eq!(mock.sent().len(), 5);
+    }
+
+    #[test]
+    fn retry_returns_last_error() {
+        let mut calls = 0;
+        let result: Result<(), Error> = retry(3, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 {
+                Err(Error::InvalidRead)
+            } else {
+                Err(Error::ReturnedError {
+                    command: 0x06,
+                    data: Vec::new(),
+                })
+            }
+        });
+        assert!(matches!(result, Err(Error::ReturnedError { .. })));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn cursor_position_cache() {
+        let (mut device, _mock) = mock::device();
+        assert_eq!(device.cursor_position(), None);
+        device.set_cursor_position(2, 7).unwrap();
+        assert_eq!(device.cursor_position(), Some((2, 7)));
+        assert!(device.set_cursor_position(2, 20).is_err());
+        assert_eq!(device.cursor_position(), Some((2, 7)));
+        device.clear_screen().unwrap();
+        assert_eq!(device.cursor_position(), Some((0, 0)));
+    }
+
+    #[test]
+    fn set_screen() {
+        let (mut device, mock) = mock::device();
+        device.set_screen("one\n\nthree\n").unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x06, &[]),
+                Packet::new(0x1f, b"\x00\x00one"),
+                Packet::new(0x1f, b"\x00\x02three"),
+            ]
+        );
+
+        assert_invalid_argument(device.set_screen("1\n2\n3\n4\n5"), "lines", 5, 4);
+        assert_invalid_argument(
+            device.set_screen("ok\n123456789012345678901"),
+            "text",
+            21,
+            20,
+        );
+        assert_eq!(mock.sent().len(), 3);
+    }
+
+    #[test]
+    fn position() {
+        let (mut device, mock) = mock::device();
+        let position = Position::new(3, 18).unwrap();
+        assert_eq!(<(u8, u8)>::from(position), (3, 18));
+        device.set_text_at(position, b"ab").unwrap();
+        device.set_cursor_position_at(Position::ORIGIN).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x1f, &[18, 3, b'a', b'b']),
+                Packet::new(0x0b, &[0, 0]),
+            ]
+        );
+        assert_invalid_argument(device.set_text_at(position, b"abc"), "text", 3, 2);
+        assert_invalid_argument(Position::new(4, 0).map(drop), "row", 4, 3);
+        assert_invalid_argument(Position::new(0, 20).map(drop), "col", 20, 19);
+    }
+
+    #[test]
+    fn move_cursor() {
+        let (mut device, mock) = mock::device();
+        assert!(matches!(
+            device.move_cursor(1, 1),
+            Err(Error::UnknownCursorPosition)
+        ));
+        device.set_cursor_position(1, 5).unwrap();
+        device.move_cursor(1, -2).unwrap();
+        assert_eq!(device.cursor_position(), Some((2, 3)));
+        device.move_cursor(5, 100).unwrap();
+        assert_eq!(device.cursor_position(), Some((3, 19)));
+        device.move_cursor(-128, -128).unwrap();
+        assert_eq!(device.cursor_position(), Some((0, 0)));
+        assert_eq!(mock.sent().last(), Some(&Packet::new(0x0b, &[0, 0])));
+    }
+
+    #[test]
+    fn set_cursor_visible() {
+        let (mut device, mock) = mock::device();
+        device.set_cursor_visible(true).unwrap();
+        device
+            .set_cursor_style(CursorStyle::StaticUnderscore)
+            .unwrap();
+        device.set_cursor_visible(false).unwrap();
+        device.set_cursor_visible(true).unwrap();
+        let styles: Vec<u8> = mock.sent().iter().map(|p| p.data()[0]).collect();
+        assert_eq!(styles, [1, 2, 0, 2]);
+    }
+
+    #[test]
+    fn invalidate_cache_resends_frame() {
+        let (mut device, mock) = mock::device();
+        let frame = Frame::new()
+            .row(0, b"hello")
+            .unwrap()
+            .cursor_position(1, 2)
+            .unwrap();
+        device.apply_frame(&frame).unwrap();
+        device.apply_frame(&frame).unwrap();
+        assert_eq!(mock.sent().len(), 2);
+        assert_eq!(device.cursor_position(), Some((1, 2)));
+
+        device.invalidate_cache();
+        assert_eq!(device.cursor_position(), None);
+        device.apply_frame(&frame).unwrap();
+        assert_eq!(mock.sent().len(), 4);
+    }
+
+    #[test]
+    fn interrupt_wait() {
+        let (mut device, _mock) = mock::device();
+        let handle = device.interrupt_handle();
+        let waiter = thread::spawn(move || {
+            let result = device.wait_report(None);
+            (device, result)
+        });
+        thread::sleep(Duration::from_millis(20));
+        handle.interrupt();
+        let (mut device, result) = waiter.join().unwrap();
+        assert!(matches!(result, Err(Error::Interrupted)));
+
+        // The interrupt is cleared once observed.
+        device.clear_screen().unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn report_serde_roundtrip() {
+        let report = Report::KeyActivity {
+            key: Key::Enter,
+            pressed: true,
+        };
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(json, r#"{"KeyActivity":{"key":"Enter","pressed":true}}"#);
+        assert_eq!(serde_json::from_str::<Report>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn batch_sends_before_reading() {
+        let (mut device, mock) = mock::device();
+        mock.queue(&Packet::new(0x80, &[1]));
+        device
+            .batch(|batch| {
+                batch.clear_screen()?;
+                batch.set_text(1, 0, b"batched")?;
+                // Nothing has been read yet.
+                assert_eq!(mock.reads(), 0);
+                batch.set_cursor_position(1, 7)
+            })
+            .unwrap();
+        assert_eq!(mock.sent().len(), 3);
+        assert_eq!(device.cursor_position(), Some((1, 7)));
+        assert_eq!(device.peek_reports().len(), 1);
+
+        mock.respond_error(0x06);
+        let result = device.batch(|batch| {
+            batch.clear_screen()?;
+            batch.set_cursor_position(0, 3)?;
+            batch.set_text(4, 0, b"")
+        });
+        assert!(matches!(
+            result,
+            Err(Error::ReturnedError { command: 0x06, .. })
+        ));
+        assert_eq!(device.cursor_position(), None);
+        // Both responses were read, so later commands are not confused.
+        mock.respond(0x00, b"next");
+        assert_eq!(device.ping(b"next").unwrap(), b"next");
+    }
+
+    #[test]
+    fn set_led_color() {
+        let (mut device, mock) = mock::device();
+        device.set_led_color(2, LedColor::ORANGE).unwrap();
+        device
+            .set_led_color(Led::ALL[0], LedColor::clamped(255, 30))
+            .unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x22, &[8, 100]),
+                Packet::new(0x22, &[7, 50]),
+                Packet::new(0x22, &[12, 100]),
+                Packet::new(0x22, &[11, 30]),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_codes() {
+        for key in Key::ALL {
+            assert_eq!(Key::from_code(key.press_code()), Some((key, true)));
+            assert_eq!(Key::from_code(key.release_code()), Some((key, false)));
+        }
+        assert_eq!(Key::from_code(5), Some((Key::Enter, true)));
+        assert_eq!(Key::from_code(10), Some((Key::Right, false)));
+        assert_eq!(Key::from_code(0), None);
+        assert_eq!(Key::from_code(13), None);
+    }
+
+    #[test]
+    fn screen_snapshot() {
+        let (mut device, _mock) = mock::device();
+        device.set_text(1, 2, b"abc").unwrap();
+        device.set_line(3, b"last").unwrap();
+        assert_eq!(device.screen_snapshot()[1], *b"  abc               ");
+        assert_eq!(device.screen_snapshot()[3], *b"last                ");
+        assert_invalid_argument(device.set_text(0, 19, b"ab"), "text", 2, 1);
+        assert_eq!(device.screen_snapshot()[0], [b' '; 20]);
+
+        device.clear_screen().unwrap();
+        assert_eq!(device.screen_snapshot(), Screen::blank().rows());
+
+        device.set_text(0, 0, b"abc").unwrap();
+        device.invalidate_cache();
+        assert_eq!(device.screen_snapshot(), Screen::blank().rows());
+        device.set_text(0, 0, b"abc").unwrap();
+        device.reboot().unwrap();
+        assert_eq!(device.screen_snapshot(), Screen::blank().rows());
+    }
+
+    #[test]
+    fn flash_message() {
+        let (mut device, mock) = mock::device();
+        device.clear_screen().unwrap();
+        device.set_text(1, 0, b"keep me").unwrap();
+        device
+            .flash_message(b"SAVED", Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(
+            mock.sent()[2..],
+            [
+                Packet::new(0x1f, b"\x00\x01       SAVED        "),
+                Packet::new(0x1f, b"\x00\x01keep me             "),
+            ]
+        );
+        assert_eq!(mock.elapsed(), Duration::from_secs(2));
+        assert_eq!(device.screen_snapshot()[1], *b"keep me             ");
+
+        assert_invalid_argument(
+            device.flash_message(&[b'!'; 21], Duration::ZERO),
+            "text",
+            21,
+            20,
+        );
+        assert_eq!(mock.sent().len(), 4);
+    }
+
+    #[test]
+    fn flash_message_reads_unknown_screen() {
+        let (mut device, mock) = mock::device();
+        let mut odd_rows = [b' '; 40];
+        odd_rows[..11].copy_from_slice(b"from before");
+        respond_ddram(&mock, [&[b' '; 40], &odd_rows]);
+        device.flash_message(b"SAVED", Duration::ZERO).unwrap();
+        let sent = mock.sent();
+        assert_eq!(sent.len(), 12);
+        assert!(sent[..10].iter().all(|packet| packet.packet_type() == 0x0a));
+        assert_eq!(sent[11], Packet::new(0x1f, b"\x00\x01from before         "));
+
+        // A failed read leaves the display untouched.
+        let (mut device, mock) = mock::device();
+        assert!(matches!(
+            device.flash_message(b"SAVED", Duration::ZERO),
+            Err(Error::InvalidRead)
+        ));
+        assert_eq!(mock.sent(), [Packet::new(0x0a, &[0x80])]);
+    }
+
+    #[test]
+    fn set_gpio() {
+        let (mut device, mock) = mock::device();
+        device.set_gpio(0, 50).unwrap();
+        assert_eq!(mock.sent(), [Packet::new(0x22, &[0, 50])]);
+        assert_invalid_argument(device.set_gpio(13, 0), "index", 13, 12);
+    }
+
+    #[test]
+    fn configure_live_display() {
+        let (mut device, mock) = mock::device();
+        let temperature = LiveDisplayItem::Temperature {
+            sensor: 3,
+            unit: TemperatureUnit::Fahrenheit,
+            tenths: true,
+        };
+        let fan = LiveDisplayItem::Fan {
+            fan: 1,
+            pulses_per_revolution: 2,
+        };
+        device
+            .configure_live_display(0, temperature, 3, 14)
+            .unwrap();
+        device.configure_live_display(7, fan, 0, 0).unwrap();
+        device
+            .configure_live_display(0, LiveDisplayItem::Disabled, 0, 0)
+            .unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x15, &[0, 2, 3, 5, 14, 3, 1]),
+                Packet::new(0x15, &[7, 1, 1, 4, 0, 0, 2]),
+                Packet::new(0x15, &[0, 0, 0, 0, 0, 0, 0]),
+            ]
+        );
+
+        assert_invalid_argument(device.configure_live_display(8, fan, 0, 0), "slot", 8, 7);
+        let fan = LiveDisplayItem::Fan {
+            fan: 1,
+            pulses_per_revolution: 0,
+        };
+        assert_invalid_argument(
+            device.configure_live_display(0, fan, 0, 0),
+            "pulses_per_revolution",
+            0,
+            8,
+        );
+        assert_eq!(mock.sent().len(), 3);
+    }
+
+    #[test]
+    fn configure_atx() {
+        let (mut device, mock) = mock::device();
+        let config = AtxConfig {
+            auto_polarity: true,
+            keypad_power_on: true,
+            keypad_power_off: true,
+            power_pulse: 16,
+            ..AtxConfig::default()
+        };
+        device.configure_atx(config).unwrap();
+        assert_eq!(mock.sent(), [Packet::new(0x1c, &[0xc1, 16])]);
+        assert_eq!(
+            AtxConfig::from_flags(0xc1),
+            AtxConfig {
+                power_pulse: 32,
+                ..config
+            }
+        );
+        assert_eq!(AtxConfig::default().flags(), 0);
+
+        let config = AtxConfig {
+            power_pulse: 0,
+            ..AtxConfig::default()
+        };
+        assert!(matches!(
+            device.configure_atx(config),
+            Err(Error::InvalidArgument {
+                field: "power_pulse",
+                value: 0,
+                min: 1,
+                max: 255,
+            })
+        ));
+        assert_eq!(
+            device.configure_atx(config).unwrap_err().to_string(),
+            "invalid value for argument `power_pulse`: 0 (expected 1 to 255)"
+        );
+    }
+
+    #[test]
+    fn set_watchdog() {
+        let (mut device, mock) = mock::device();
+        device.set_watchdog(30).unwrap();
+        device.set_watchdog(0).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(0x1d, &[30]), Packet::new(0x1d, &[0])]
+        );
+    }
+
+    #[test]
+    fn flush_input() {
+        let (mut device, mock) = mock::device();
+        mock.queue(&Packet::new(0x80, &[1]));
+        mock.respond_bytes(0x00, vec![0x46, 0x00]);
+        device.send_no_wait(0x00, &[]).unwrap();
+        // Leave a partial packet in the codec's buffer.
+        assert!(device.poll_report().unwrap().is_some());
+        assert!(device.poll_report().is_err());
+        device.flush_input().unwrap();
+        assert_eq!(device.poll_report().unwrap(), None);
+        device.clear_screen().unwrap();
+    }
+
+    #[test]
+    fn resync_after_corrupted_response() {
+        let (mut device, mock) = mock::device();
+        let mut bytes = vec![0x46, 0x02, 0xff];
+        bytes.extend(Packet::new(0x46, &[]).to_bytes());
+        mock.respond_bytes(0x06, bytes);
+        device.clear_screen().unwrap();
+        device.ping(b"still in sync").unwrap();
+    }
+
+    #[test]
+    fn wrong_response_length() {
+        let (mut device, mock) = mock::device();
+        mock.respond(0x06, &[0]);
+        assert!(matches!(device.clear_screen(), Err(Error::InvalidRead)));
+
+        mock.respond(0x00, b"hell");
+        assert!(matches!(device.ping(b"hello"), Err(Error::InvalidRead)));
+
+        // Commands with variable length responses are not checked here.
+        let mut data = [0; 17];
+        data[13] = 120;
+        mock.respond(0x1e, &data);
+        assert_eq!(device.read_status().unwrap().contrast, 120);
+    }
+
+    #[test]
+    fn set_cursor_position_out_of_bounds() {
+        let (mut device, mock) = mock::device();
+        assert_invalid_argument(device.set_cursor_position(200, 0), "row", 200, 3);
+        assert_invalid_argument(device.set_cursor_position(3, 25), "col", 25, 19);
+        assert!(mock.sent().is_empty());
+    }
+
+    #[test]
+    fn led_gpio_pins() {
+        assert_eq!(Device::led_gpio_pins(0), Some((12, 11)));
+        assert_eq!(Device::led_gpio_pins(3), Some((6, 5)));
+        assert_eq!(Device::led_gpio_pins(NUM_LEDS), None);
+    }
+
+    #[test]
+    fn set_contrast_unchanged() {
+        let (mut device, mock) = mock::device();
+        device.set_contrast(None).unwrap();
+        assert!(mock.sent().is_empty());
+
+        device.set_contrast(130).unwrap();
+        device.set_contrast(None).unwrap();
+        device.set_contrast(Contrast::dark()).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x0d, &[130]),
+                Packet::new(0x0d, &[130]),
+                Packet::new(0x0d, &[150]),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_backlight_unchanged() {
+        let (mut device, mock) = mock::device();
+        device.set_backlight(None, None).unwrap();
+        assert!(mock.sent().is_empty());
+
+        device.set_backlight(80, 20).unwrap();
+        device.set_backlight(None, 50).unwrap();
+        device.set_backlight(10, None).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x0e, &[80, 20]),
+                Packet::new(0x0e, &[80, 50]),
+                Packet::new(0x0e, &[10, 50]),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_backlight_unchanged_reads_status() {
+        let (mut device, mock) = mock::device();
+        let mut data = [0; 16];
+        data[14] = 70;
+        data[15] = 30;
+        mock.respond(0x1e, &data);
+        device.set_backlight(None, 100).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [Packet::new(0x1e, &[]), Packet::new(0x0e, &[70, 100])]
+        );
+    }
+
+    #[test]
+    fn set_brightness() {
+        let (mut device, mock) = mock::device();
+        device.set_brightness(60).unwrap();
+        device.set_screen_backlight(0).unwrap();
+        device.set_keypad_backlight(200).unwrap();
+        assert_eq!(
+            mock.sent(),
+            [
+                Packet::new(0x0e, &[60, 60]),
+                Packet::new(0x0e, &[0, 60]),
+                Packet::new(0x0e, &[0, 100]),
+            ]
+        );
+    }
+
+    #[test]
+    fn is_alive() {
+        let (mut device, mock) = mock::device();
+        assert!(device.is_alive());
+        mock.respond_error(0x00);
+        assert!(!device.is_alive());
+        mock.respond_bytes(0x00, Vec::new());
+        assert!(!device.is_alive());
+        assert_eq!(mock.sent().len(), 3);
+    }
+
+    #[test]
+    fn set_all_leds() {
+        let (mut device, mock) = mock::device();
+        device
+            .set_all_leds([
+                LedColor::RED,
+                LedColor::GREEN,
+                LedColor::OFF,
+                LedColor::YELLOW,
+            ])
+            .unwrap();
+        let writes: Vec<Vec<u8>> = mock.sent().iter().map(|p| p.data().to_vec()).collect();
+        assert_eq!(
+            writes,
+            [
+                [12, 100],
+                [11, 0],
+                [10, 0],
+                [9, 100],
+                [8, 0],
+                [7, 0],
+                [6, 100],
+                [5, 100],
+            ]
+        );
+        // Colors are remembered as with `set_led`.
+        device.led_on(0, None).unwrap();
+        assert_eq!(mock.sent()[8].data(), [12, 100]);
+    }
+
+    #[test]
+    fn led_on_restores_last_color() {
+        let (mut device, mock) = mock::device();
+        device.led_on(1, None).unwrap();
+        device.led_on(1, LedColor::new(100, 50)).unwrap();
+        device.led_off(1).unwrap();
+        device.led_on(1, None).unwrap();
+        let writes: Vec<Vec<u8>> = mock.sent().iter().map(|p| p.data().to_vec()).collect();
+        assert_eq!(
+            writes,
+            [
+                // Default color
+                [10, 0],
+                [9, 100],
+                // Explicit color
+                [10, 100],
+                [9, 50],
+                // Off
+                [10, 0],
+                [9, 0],
+                // Restored
+                [10, 100],
+                [9, 50],
+            ]
+        );
+    }
+
+    #[test]
+    fn set_led_out_of_bounds() {
+        let (mut device, mock) = mock::device();
+        assert_invalid_argument(device.set_led(4, 0, 0), "index", 4, 3);
+        assert!(mock.sent().is_empty());
     }
 }
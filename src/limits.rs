@@ -0,0 +1,59 @@
+//! Hard limits of the CFA635 protocol.
+
+pub use crate::codec::MAX_DATA_LEN;
+
+/// The maximum length of the text passed to
+/// [`Device::set_text`](crate::Device::set_text), in bytes.
+///
+/// This is the width of the screen. Text starting at a column other than 0
+/// must also end at or before the right edge of the row, so it can be at most
+/// `MAX_TEXT_LEN - col` bytes long. The text packet's two position bytes
+/// plus `MAX_TEXT_LEN` bytes of text fit within [`MAX_DATA_LEN`].
+pub const MAX_TEXT_LEN: usize = crate::NUM_COLUMNS as usize;
+
+/// The largest command code.
+///
+/// The two high bits of a packet's type identify the kind of packet (command,
+/// response, report or error), leaving six bits for the command code.
+pub const MAX_COMMAND: u8 = 0x3f;
+
+/// The maximum payload length of a ping (see
+/// [`Device::ping`](crate::Device::ping)).
+pub const MAX_PING_LEN: usize = 16;
+
+/// The number of custom character slots in the display's character generator
+/// RAM (CGRAM).
+///
+/// Custom characters are displayed using the bytes `0..CGRAM_SLOTS`.
+pub const CGRAM_SLOTS: u8 = 8;
+
+/// The width of a custom character in pixels.
+///
+/// Each row of a custom character bitmap is one byte, of which the low
+/// `CUSTOM_CHAR_WIDTH` bits are used, with the most significant of those bits
+/// on the left.
+pub const CUSTOM_CHAR_WIDTH: u8 = 6;
+
+/// The height of a custom character in pixels, i.e. the number of rows in its
+/// bitmap.
+pub const CUSTOM_CHAR_HEIGHT: u8 = 8;
+
+/// The size of the non-volatile user flash area in bytes (see
+/// [`Device::write_user_flash`](crate::Device::write_user_flash)).
+pub const USER_FLASH_LEN: usize = 16;
+
+/// The number of live display slots (see
+/// [`Device::configure_live_display`](crate::Device::configure_live_display)).
+pub const LIVE_DISPLAY_SLOTS: u8 = 8;
+
+/// The number of temperature sensors that can be connected through a
+/// fan/temperature expansion board.
+pub const TEMPERATURE_SENSORS: u8 = 32;
+
+/// The number of fans that can be connected through a fan/temperature
+/// expansion board.
+pub const FANS: u8 = 4;
+
+/// The highest LCD contrast value (see
+/// [`Device::set_contrast`](crate::Device::set_contrast)).
+pub const MAX_CONTRAST: u8 = 254;
@@ -0,0 +1,139 @@
+use crate::{check_range, encode_str, Error, NUM_COLUMNS, NUM_ROWS};
+
+/// The default number of spaces between repeats of a [`Marquee`]'s text.
+pub const DEFAULT_MARQUEE_GAP: usize = 3;
+
+/// Text that scrolls horizontally across a row, for messages longer than the
+/// screen is wide.
+///
+/// The marquee is drawn with [`Device::step_marquee`](crate::Device::step_marquee),
+/// which shows the current window and scrolls the text left by one column.
+/// After the end of the text, a gap of spaces is shown before the text
+/// repeats. Text that fits on the row is shown as-is, without scrolling.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::{Device, Marquee};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let mut device = Device::new("/dev/ttyACM0")?;
+/// let mut marquee = Marquee::new(0, "Uptime 12 days, load 0.42, 3 users")?.gap(5);
+/// loop {
+///     device.step_marquee(&mut marquee)?;
+///     thread::sleep(Duration::from_millis(300));
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marquee {
+    row: u8,
+    text: Vec<u8>,
+    gap: usize,
+    position: usize,
+}
+
+impl Marquee {
+    /// Creates a marquee showing `text` on the given row, starting with the
+    /// beginning of the text at the left edge.
+    ///
+    /// The text is converted to the display's character set, as
+    /// [`Device::set_str`](crate::Device::set_str) does.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If the row index is out of bounds (as defined by
+    ///   [`NUM_ROWS`]). The error's `field` is `"row"`.
+    ///
+    /// - `UnmappableCharacter` - If the text contains a character the display
+    ///   cannot show.
+    pub fn new(row: u8, text: &str) -> Result<Self, Error> {
+        check_range("row", row, NUM_ROWS)?;
+        Ok(Self {
+            row,
+            text: encode_str(text)?,
+            gap: DEFAULT_MARQUEE_GAP,
+            position: 0,
+        })
+    }
+
+    /// Sets the number of spaces shown between the end of the text and its
+    /// next repeat.
+    ///
+    /// The default is [`DEFAULT_MARQUEE_GAP`].
+    pub fn gap(mut self, gap: usize) -> Self {
+        self.gap = gap;
+        self.position = 0;
+        self
+    }
+
+    /// Returns the row the marquee is shown on.
+    pub fn row(&self) -> u8 {
+        self.row
+    }
+
+    /// Returns the characters currently visible on the row.
+    pub fn window(&self) -> [u8; NUM_COLUMNS as usize] {
+        let mut window = [b' '; NUM_COLUMNS as usize];
+        if !self.scrolls() {
+            window[..self.text.len()].copy_from_slice(&self.text);
+            return window;
+        }
+        let cycle = self.cycle_len();
+        for (i, byte) in window.iter_mut().enumerate() {
+            // Positions past the end of the text are in the gap.
+            if let Some(&c) = self.text.get((self.position + i) % cycle) {
+                *byte = c;
+            }
+        }
+        window
+    }
+
+    /// Scrolls the text left by one column.
+    pub(crate) fn advance(&mut self) {
+        if self.scrolls() {
+            self.position = (self.position + 1) % self.cycle_len();
+        }
+    }
+
+    fn scrolls(&self) -> bool {
+        self.text.len() > usize::from(NUM_COLUMNS)
+    }
+
+    fn cycle_len(&self) -> usize {
+        self.text.len() + self.gap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolls_with_gap() {
+        let mut marquee = Marquee::new(1, "abcdefghijklmnopqrstuvwxyz")
+            .unwrap()
+            .gap(2);
+        assert_eq!(&marquee.window(), b"abcdefghijklmnopqrst");
+        for _ in 0..7 {
+            marquee.advance();
+        }
+        assert_eq!(&marquee.window(), b"hijklmnopqrstuvwxyz ");
+        marquee.advance();
+        assert_eq!(&marquee.window(), b"ijklmnopqrstuvwxyz  ");
+        marquee.advance();
+        assert_eq!(&marquee.window(), b"jklmnopqrstuvwxyz  a");
+        for _ in 0..19 {
+            marquee.advance();
+        }
+        assert_eq!(&marquee.window(), b"abcdefghijklmnopqrst");
+    }
+
+    #[test]
+    fn short_text_is_static() {
+        let mut marquee = Marquee::new(0, "short").unwrap();
+        marquee.advance();
+        assert_eq!(&marquee.window(), b"short               ");
+        assert!(Marquee::new(4, "x").is_err());
+    }
+}
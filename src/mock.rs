@@ -0,0 +1,211 @@
+//! An in-memory transport for testing [`Device`] without hardware.
+
+use crate::clock::Clock;
+use crate::codec::{Packet, PacketCodec};
+use crate::transport::Transport;
+use crate::Device;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Creates a device connected to a mock transport, along with a handle to
+/// inspect and script the transport.
+///
+/// The device uses a virtual clock, which only advances when the device
+/// sleeps or a read is delayed (see [`MockHandle::set_read_delay`]).
+pub(crate) fn device() -> (Device, MockHandle) {
+    let state = Arc::new(Mutex::new(MockState::default()));
+    let transport = MockTransport {
+        state: state.clone(),
+    };
+    let start = Instant::now();
+    let mut device = Device::from_transport(transport);
+    device.clock = Box::new(MockClock {
+        start,
+        state: state.clone(),
+    });
+    (device, MockHandle { start, state })
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Bytes written by the device that have not been flushed yet.
+    written: Vec<u8>,
+    /// All packets sent by the device.
+    sent: Vec<Packet>,
+    /// The value of `reads` when each packet in `sent` was sent.
+    reads_before_sent: Vec<usize>,
+    /// Bytes waiting to be read by the device.
+    readable: VecDeque<u8>,
+    /// Scripted responses as raw bytes, keyed by command code.
+    responses: HashMap<u8, VecDeque<Vec<u8>>>,
+    /// The number of calls to `read`.
+    reads: usize,
+    /// How long each call to `read` takes.
+    read_delay: Duration,
+    /// How far the virtual clock has advanced.
+    elapsed: Duration,
+}
+
+impl MockState {
+    fn queue(&mut self, packet: &Packet) {
+        self.readable.extend(packet.to_bytes());
+    }
+
+    /// Records the flushed packets and queues a response for each of them.
+    fn process_written(&mut self) {
+        let written = std::mem::take(&mut self.written);
+        for packet in PacketCodec::new(written.as_slice()).packets() {
+            let packet = packet.expect("device wrote an invalid packet");
+            let command = packet.packet_type();
+            match self
+                .responses
+                .get_mut(&command)
+                .and_then(VecDeque::pop_front)
+            {
+                Some(response) => self.readable.extend(response),
+                // Ping echoes its payload; everything else gets an empty ack.
+                None if command == 0x00 => self.queue(&Packet::new(0x40, packet.data())),
+                None => self.queue(&Packet::new(0x40 | command, &[])),
+            }
+            self.sent.push(packet);
+            self.reads_before_sent.push(self.reads);
+        }
+    }
+}
+
+struct MockClock {
+    start: Instant,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.state.lock().unwrap().elapsed
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.state.lock().unwrap().elapsed += duration;
+    }
+}
+
+struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Read for MockTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let delay = state.read_delay;
+        state.elapsed += delay;
+        state.reads += 1;
+        if state.readable.is_empty() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        let len = buf.len().min(state.readable.len());
+        for (dst, src) in buf.iter_mut().zip(state.readable.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+}
+
+impl Write for MockTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.state.lock().unwrap().written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state.lock().unwrap().process_written();
+        Ok(())
+    }
+}
+
+impl Transport for MockTransport {
+    fn bytes_to_read(&self) -> io::Result<u32> {
+        Ok(self.state.lock().unwrap().readable.len() as u32)
+    }
+}
+
+/// Inspects and scripts the mock transport of a test device.
+#[derive(Clone)]
+pub(crate) struct MockHandle {
+    /// The time at which the virtual clock started.
+    start: Instant,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockHandle {
+    /// Returns all packets sent by the device so far.
+    pub fn sent(&self) -> Vec<Packet> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    /// Returns the number of reads the device has attempted so far.
+    pub fn reads(&self) -> usize {
+        self.state.lock().unwrap().reads
+    }
+
+    /// Returns, for each packet in [`MockHandle::sent`], the number of reads
+    /// the device had attempted before sending it.
+    pub fn reads_before_sent(&self) -> Vec<usize> {
+        self.state.lock().unwrap().reads_before_sent.clone()
+    }
+
+    /// Makes every read take `delay` on the device's virtual clock, to
+    /// simulate a slow link.
+    pub fn set_read_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().read_delay = delay;
+    }
+
+    /// Returns how far the device's virtual clock has advanced.
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().elapsed
+    }
+
+    /// Returns the current time on the device's virtual clock.
+    pub fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    /// Advances the device's virtual clock by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.state.lock().unwrap().elapsed += duration;
+    }
+
+    /// Queues a normal response with the given data for the next time the
+    /// command is sent.
+    pub fn respond(&self, command: u8, data: &[u8]) {
+        self.respond_with(command, Packet::new(0x40 | command, data));
+    }
+
+    /// Queues an error response for the next time the command is sent.
+    pub fn respond_error(&self, command: u8) {
+        self.respond_with(command, Packet::new(0xc0 | command, &[]));
+    }
+
+    /// Makes a packet (e.g. a report) available to read immediately.
+    pub fn queue(&self, packet: &Packet) {
+        self.state.lock().unwrap().queue(packet);
+    }
+
+    /// Queues an arbitrary response packet for the next time the command is
+    /// sent.
+    pub fn respond_with(&self, command: u8, packet: Packet) {
+        self.respond_bytes(command, packet.to_bytes());
+    }
+
+    /// Queues raw bytes (e.g. a corrupted packet) as the response for the next
+    /// time the command is sent.
+    pub fn respond_bytes(&self, command: u8, bytes: Vec<u8>) {
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .entry(command)
+            .or_default()
+            .push_back(bytes);
+    }
+}
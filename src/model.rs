@@ -0,0 +1,101 @@
+use std::fmt;
+
+/// A Crystalfontz display model that speaks the same packet protocol as the
+/// CFA635.
+///
+/// The model decides which GPIO pins drive the indicator LEDs (see
+/// [`Model::led_gpio_pins`]). It is [`Model::Cfa635`] unless chosen with
+/// [`DeviceBuilder::model`](crate::DeviceBuilder::model), or detected from
+/// the device's version string with
+/// [`DeviceBuilder::detect_model`](crate::DeviceBuilder::detect_model).
+/// Everything else, including the screen size ([`NUM_ROWS`](crate::NUM_ROWS)
+/// and [`NUM_COLUMNS`](crate::NUM_COLUMNS)), still assumes a CFA635.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::Device;
+///
+/// let device = Device::builder("/dev/ttyACM0").detect_model(true).open()?;
+/// println!("connected to a {}", device.model());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Model {
+    /// The CFA635, with four red/green LEDs to the left of the screen.
+    ///
+    /// | LED | Red pin | Green pin |
+    /// |-----|---------|-----------|
+    /// | 0   | 12      | 11        |
+    /// | 1   | 10      | 9         |
+    /// | 2   | 8       | 7         |
+    /// | 3   | 6       | 5         |
+    #[default]
+    Cfa635,
+
+    /// The CFA633, which has no indicator LEDs.
+    Cfa633,
+}
+
+impl Model {
+    /// Looks up a model by the name it reports in its version string (see
+    /// [`Version::model`](crate::Version::model)), e.g. `CFA635`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "CFA635" => Some(Self::Cfa635),
+            "CFA633" => Some(Self::Cfa633),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the model, as it reports in its version string.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cfa635 => "CFA635",
+            Self::Cfa633 => "CFA633",
+        }
+    }
+
+    /// Returns the GPIO pins that drive the red and green components of an
+    /// indicator LED on this model, as `(red, green)`.
+    ///
+    /// Returns `None` if the LED index is out of bounds, or if the model has
+    /// no LEDs. See the documentation of each model for its table.
+    pub fn led_gpio_pins(self, index: u8) -> Option<(u8, u8)> {
+        match (self, index) {
+            (Self::Cfa635, 0) => Some((12, 11)),
+            (Self::Cfa635, 1) => Some((10, 9)),
+            (Self::Cfa635, 2) => Some((8, 7)),
+            (Self::Cfa635, 3) => Some((6, 5)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for model in [Model::Cfa635, Model::Cfa633] {
+            assert_eq!(Model::from_name(model.name()), Some(model));
+        }
+        assert_eq!(Model::from_name("CFA631"), None);
+        assert_eq!(Model::Cfa633.to_string(), "CFA633");
+    }
+
+    #[test]
+    fn led_tables() {
+        assert_eq!(Model::Cfa635.led_gpio_pins(0), Some((12, 11)));
+        assert_eq!(Model::Cfa635.led_gpio_pins(4), None);
+        assert_eq!(Model::Cfa633.led_gpio_pins(0), None);
+    }
+}
@@ -0,0 +1,36 @@
+//! One-shot helpers that connect to a device, perform a single action, and
+//! disconnect.
+//!
+//! These are convenient for scripts and command-line tools that only need to
+//! do one thing. Each call opens and closes the serial port, so they are
+//! inefficient for repeated use; keep a [`Device`] around instead.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), cfa635::Error> {
+//! cfa635::quick::write("/dev/ttyACM0", 0, 0, b"Hello, world!")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Device, Error};
+
+/// Clears all text from the screen.
+///
+/// See [`Device::clear_screen`].
+pub fn clear<P: AsRef<str>>(path: P) -> Result<(), Error> {
+    Device::new(path)?.clear_screen()
+}
+
+/// Blanks the display by turning off the screen and keypad backlights.
+///
+/// See [`Device::set_backlight`].
+pub fn blank<P: AsRef<str>>(path: P) -> Result<(), Error> {
+    Device::new(path)?.set_brightness(0)
+}
+
+/// Writes text to the screen at the given position.
+///
+/// See [`Device::set_text`] for details and errors.
+pub fn write<P: AsRef<str>>(path: P, row: u8, col: u8, text: &[u8]) -> Result<(), Error> {
+    Device::new(path)?.set_text(row, col, text)
+}
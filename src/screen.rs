@@ -0,0 +1,129 @@
+use crate::{NUM_COLUMNS, NUM_ROWS};
+
+/// The characters shown on the display, as read back by
+/// [`Device::read_screen`](crate::Device::read_screen).
+///
+/// Characters are character ROM codes (or custom character slots), not
+/// Unicode. See [`charset`](crate::charset) for the mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    pub(crate) rows: [[u8; NUM_COLUMNS as usize]; NUM_ROWS as usize],
+}
+
+impl Screen {
+    /// Creates a screen filled with spaces, like a cleared display.
+    pub fn blank() -> Self {
+        Self {
+            rows: [[b' '; NUM_COLUMNS as usize]; NUM_ROWS as usize],
+        }
+    }
+
+    /// Returns the characters of the given row, or `None` if the row index is
+    /// out of bounds.
+    pub fn row(&self, row: u8) -> Option<&[u8; NUM_COLUMNS as usize]> {
+        self.rows.get(usize::from(row))
+    }
+
+    /// Returns all rows, from top to bottom.
+    pub fn rows(&self) -> &[[u8; NUM_COLUMNS as usize]; NUM_ROWS as usize] {
+        &self.rows
+    }
+
+    /// Returns the character at the given position, or `None` if it is out of
+    /// bounds.
+    pub fn char_at(&self, row: u8, col: u8) -> Option<u8> {
+        self.row(row)?.get(usize::from(col)).copied()
+    }
+
+    /// Returns the range of columns in `row` that differ from `previous`, as
+    /// `(first, last)` inclusive, or `None` if the row is unchanged.
+    pub(crate) fn changed_span(&self, previous: &Screen, row: u8) -> Option<(u8, u8)> {
+        let (new, old) = (self.row(row)?, previous.row(row)?);
+        let changed = |&col: &u8| new[usize::from(col)] != old[usize::from(col)];
+        let first = (0..NUM_COLUMNS).find(changed)?;
+        let last = (0..NUM_COLUMNS).rev().find(changed)?;
+        Some((first, last))
+    }
+
+    /// Returns the number of text packets (command `0x1F`) needed to update
+    /// the display from `previous` to this screen.
+    ///
+    /// A single packet can hold an entire row, so each row with any changes
+    /// takes one packet, covering the first through the last changed
+    /// character. Unchanged rows take none.
+    pub fn update_packet_count(&self, previous: &Screen) -> usize {
+        (0..NUM_ROWS)
+            .filter(|&row| self.changed_span(previous, row).is_some())
+            .count()
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::blank()
+    }
+}
+
+/// DDRAM address of the first character of each row.
+///
+/// The display controller uses the HD44780 layout for 4-line displays, where
+/// rows are not contiguous in memory:
+///
+/// | Row | Addresses     |
+/// |-----|---------------|
+/// | 0   | `0x00..=0x13` |
+/// | 1   | `0x40..=0x53` |
+/// | 2   | `0x14..=0x27` |
+/// | 3   | `0x54..=0x67` |
+///
+/// Rows 0 and 2, and rows 1 and 3, form two contiguous 40-byte regions.
+pub(crate) const ROW_ADDRESSES: [u8; NUM_ROWS as usize] = [0x00, 0x40, 0x14, 0x54];
+
+/// Returns the row and column shown at the given DDRAM address, if any.
+pub(crate) fn ddram_position(address: u8) -> Option<(u8, u8)> {
+    (0..NUM_ROWS).find_map(|row| {
+        let col = address.checked_sub(ROW_ADDRESSES[usize::from(row)])?;
+        (col < NUM_COLUMNS).then_some((row, col))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_packet_count() {
+        let blank = Screen::blank();
+        assert_eq!(blank.update_packet_count(&blank), 0);
+
+        let mut one = Screen::blank();
+        one.rows[2][7] = b'x';
+        assert_eq!(one.update_packet_count(&blank), 1);
+        assert_eq!(one.changed_span(&blank, 2), Some((7, 7)));
+        assert_eq!(one.changed_span(&blank, 1), None);
+
+        let mut spread = one.clone();
+        spread.rows[2][0] = b'x';
+        spread.rows[2][19] = b'x';
+        assert_eq!(spread.update_packet_count(&blank), 1);
+        assert_eq!(spread.changed_span(&blank, 2), Some((0, 19)));
+
+        let full = Screen {
+            rows: [[b'#'; NUM_COLUMNS as usize]; NUM_ROWS as usize],
+        };
+        assert_eq!(full.update_packet_count(&blank), 4);
+    }
+
+    #[test]
+    fn ddram_map() {
+        assert_eq!(ddram_position(0x00), Some((0, 0)));
+        assert_eq!(ddram_position(0x13), Some((0, 19)));
+        assert_eq!(ddram_position(0x14), Some((2, 0)));
+        assert_eq!(ddram_position(0x27), Some((2, 19)));
+        assert_eq!(ddram_position(0x28), None);
+        assert_eq!(ddram_position(0x40), Some((1, 0)));
+        assert_eq!(ddram_position(0x54), Some((3, 0)));
+        assert_eq!(ddram_position(0x67), Some((3, 19)));
+        assert_eq!(ddram_position(0x68), None);
+    }
+}
@@ -0,0 +1,107 @@
+use serialport::SerialPort;
+use std::io::{self, Read, Write};
+
+/// A byte stream connected to the device.
+///
+/// This is the subset of [`SerialPort`] functionality that [`Device`] relies
+/// on, so that it is not tied to a particular serial port implementation.
+/// Implement it to connect a [`Device`] to something other than a serial
+/// port with [`Device::from_transport`], e.g. a fake display for testing
+/// application code without hardware.
+///
+/// Reads should time out like a serial port does, by returning an error of
+/// kind [`TimedOut`](io::ErrorKind::TimedOut) if no data arrives in time.
+/// Otherwise, a command that never receives a response blocks forever.
+///
+/// ```
+/// use cfa635::{Device, Transport};
+/// use std::io::{self, Read, Write};
+///
+/// /// Accepts every command, but never responds.
+/// struct Sink;
+///
+/// impl Read for Sink {
+///     fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+///         Err(io::ErrorKind::TimedOut.into())
+///     }
+/// }
+///
+/// impl Write for Sink {
+///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+///         Ok(buf.len())
+///     }
+///
+///     fn flush(&mut self) -> io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// impl Transport for Sink {
+///     fn bytes_to_read(&self) -> io::Result<u32> {
+///         Ok(0)
+///     }
+/// }
+///
+/// let mut device = Device::from_transport(Sink);
+/// assert!(device.clear_screen().is_err());
+/// assert_eq!(device.poll_report().unwrap(), None);
+/// ```
+///
+/// [`Device`]: crate::Device
+/// [`Device::from_transport`]: crate::Device::from_transport
+pub trait Transport: Read + Write + Send {
+    /// Returns the number of bytes that are available to read without
+    /// blocking.
+    fn bytes_to_read(&self) -> io::Result<u32>;
+
+    /// Returns the name of the underlying port, if it has one.
+    fn port_name(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the baud rate of the underlying port, if it has one.
+    fn baud_rate(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl Transport for Box<dyn SerialPort> {
+    fn bytes_to_read(&self) -> io::Result<u32> {
+        Ok(SerialPort::bytes_to_read(self.as_ref())?)
+    }
+
+    fn port_name(&self) -> Option<String> {
+        SerialPort::name(self.as_ref())
+    }
+
+    fn baud_rate(&self) -> Option<u32> {
+        SerialPort::baud_rate(self.as_ref()).ok()
+    }
+}
+
+/// A transport that is not connected to anything.
+///
+/// Reads always return EOF and writes are discarded.
+pub(crate) struct NullTransport;
+
+impl Read for NullTransport {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for NullTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for NullTransport {
+    fn bytes_to_read(&self) -> io::Result<u32> {
+        Ok(0)
+    }
+}
@@ -0,0 +1,173 @@
+//! Graphical widgets drawn with custom characters.
+
+use crate::charset::{glyph_from_grid, GlyphBitmap};
+use crate::limits::{CGRAM_SLOTS, CUSTOM_CHAR_HEIGHT, CUSTOM_CHAR_WIDTH};
+use crate::{check_bounds, check_range, Device, Error, NUM_COLUMNS, NUM_ROWS};
+
+/// A vertical bar spanning one or more rows, filled from the bottom, e.g. for
+/// a VU meter or per-core CPU load.
+///
+/// Each character cell is [`CUSTOM_CHAR_HEIGHT`] pixels tall, so a bar over
+/// `rows` rows can show `8 * rows` levels above empty. A value of `max` fills
+/// the bar, and values in between are rounded to the nearest level.
+///
+/// Partially filled cells are drawn with custom characters. The bar uses all
+/// [`CGRAM_SLOTS`] slots: slot `n` holds a cell with its bottom `n + 1` pixel
+/// rows lit, so slot 7 is a full cell. They are loaded on the first
+/// [`VBar::render`], replacing any other custom characters. Bars share the
+/// same slots, so any number of them can be shown side by side, but they
+/// cannot be combined with other custom characters.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cfa635::Error> {
+/// use cfa635::widgets::VBar;
+/// use cfa635::Device;
+///
+/// let mut device = Device::new("/dev/ttyACM0")?;
+/// // A 4-row bar, with 32 levels for values from 0 to 100.
+/// let mut bar = VBar::new(0, 4, 100)?;
+/// for (col, load) in [12, 55, 98, 0].into_iter().enumerate() {
+///     bar.render(&mut device, col as u8, load)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VBar {
+    top: u8,
+    rows: u8,
+    max: u32,
+    /// Whether the level glyphs have been loaded into CGRAM.
+    loaded: bool,
+}
+
+impl VBar {
+    /// Creates a bar covering `rows` rows, starting at row `top`, for values
+    /// from 0 to `max`.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If `top` is out of bounds (as defined by
+    ///   [`NUM_ROWS`]), if `rows` is 0 or the bar extends past the bottom of
+    ///   the screen, or if `max` is 0. The error's `field` is `"top"`,
+    ///   `"rows"` or `"max"` respectively.
+    pub fn new(top: u8, rows: u8, max: u32) -> Result<Self, Error> {
+        check_range("top", top, NUM_ROWS)?;
+        check_bounds("rows", rows.into(), 1, (NUM_ROWS - top).into())?;
+        check_bounds("max", max as usize, 1, u32::MAX as usize)?;
+        Ok(Self {
+            top,
+            rows,
+            max,
+            loaded: false,
+        })
+    }
+
+    /// Returns the number of levels the bar can show above empty, i.e. its
+    /// height in pixels.
+    pub fn levels(&self) -> u32 {
+        u32::from(self.rows) * u32::from(CUSTOM_CHAR_HEIGHT)
+    }
+
+    /// Returns the characters that show `value`, one per row from top to
+    /// bottom: a space for an empty cell, or the slot of its level glyph.
+    ///
+    /// Values above `max` are shown as `max`.
+    pub fn cells(&self, value: u32) -> Vec<u8> {
+        let max = u64::from(self.max);
+        let lit = (u64::from(value.min(self.max)) * u64::from(self.levels()) + max / 2) / max;
+        (0..self.rows)
+            .rev()
+            .map(|cell| {
+                let below = u64::from(cell) * u64::from(CUSTOM_CHAR_HEIGHT);
+                match lit.saturating_sub(below).min(CUSTOM_CHAR_HEIGHT.into()) {
+                    0 => b' ',
+                    n => n as u8 - 1,
+                }
+            })
+            .collect()
+    }
+
+    /// Draws the bar showing `value` in column `col`.
+    ///
+    /// The first call loads the level glyphs into CGRAM (see [`VBar`]). If
+    /// the custom characters are changed by other means, e.g. by rebooting
+    /// the device, create a new `VBar` so that they are loaded again.
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidArgument` - If `col` is out of bounds (as defined by
+    ///   [`NUM_COLUMNS`]). The error's `field` is `"col"`.
+    pub fn render(&mut self, device: &mut Device, col: u8, value: u32) -> Result<(), Error> {
+        check_range("col", col, NUM_COLUMNS)?;
+        if !self.loaded {
+            for slot in 0..CGRAM_SLOTS {
+                device.set_custom_character(slot, level_glyph(slot + 1))?;
+            }
+            self.loaded = true;
+        }
+        for (row, code) in (self.top..).zip(self.cells(value)) {
+            device.set_text(row, col, &[code])?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a custom character with its bottom `lit` pixel rows turned on.
+fn level_glyph(lit: u8) -> GlyphBitmap {
+    let mut grid = [[false; CUSTOM_CHAR_WIDTH as usize]; CUSTOM_CHAR_HEIGHT as usize];
+    for row in &mut grid[usize::from(CUSTOM_CHAR_HEIGHT - lit)..] {
+        *row = [true; CUSTOM_CHAR_WIDTH as usize];
+    }
+    glyph_from_grid(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Packet;
+    use crate::mock;
+
+    #[test]
+    fn value_to_rows() {
+        let bar = VBar::new(1, 3, 240).unwrap();
+        assert_eq!(bar.levels(), 24);
+        assert_eq!(bar.cells(0), b"   ");
+        // 10 per level, rounded to the nearest.
+        assert_eq!(bar.cells(4), b"   ");
+        assert_eq!(bar.cells(5), [b' ', b' ', 0]);
+        assert_eq!(bar.cells(80), [b' ', b' ', 7]);
+        assert_eq!(bar.cells(130), [b' ', 4, 7]);
+        assert_eq!(bar.cells(240), [7, 7, 7]);
+        assert_eq!(bar.cells(1000), [7, 7, 7]);
+
+        assert!(VBar::new(1, 4, 10).is_err());
+        assert!(VBar::new(0, 0, 10).is_err());
+        assert!(VBar::new(0, 1, 0).is_err());
+    }
+
+    #[test]
+    fn render_loads_glyphs_once() {
+        let (mut device, mock) = mock::device();
+        let mut bar = VBar::new(2, 2, 16).unwrap();
+        bar.render(&mut device, 5, 11).unwrap();
+        bar.render(&mut device, 6, 16).unwrap();
+        let sent = mock.sent();
+        assert_eq!(sent.len(), usize::from(CGRAM_SLOTS) + 4);
+        assert_eq!(sent[0], Packet::new(0x09, &[0, 0, 0, 0, 0, 0, 0, 0, 0x3f]));
+        assert_eq!(
+            sent[7],
+            Packet::new(0x09, &[7, 0x3f, 0x3f, 0x3f, 0x3f, 0x3f, 0x3f, 0x3f, 0x3f])
+        );
+        assert_eq!(
+            sent[8..],
+            [
+                Packet::new(0x1f, &[5, 2, 2]),
+                Packet::new(0x1f, &[5, 3, 7]),
+                Packet::new(0x1f, &[6, 2, 7]),
+                Packet::new(0x1f, &[6, 3, 7]),
+            ]
+        );
+        assert!(bar.render(&mut device, 20, 0).is_err());
+    }
+}